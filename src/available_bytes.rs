@@ -0,0 +1,33 @@
+//! Non-blocking hints about how many bytes a reader can deliver right away.
+
+use std::io::Cursor;
+
+use futures_io::AsyncRead;
+
+/// A reader that may know how many bytes it can deliver without returning `Pending`.
+///
+/// This can be used to size read buffers to exactly the available data rather than to a fixed
+/// chunk size.
+pub trait AvailableBytes: AsyncRead {
+    /// Returns `Some(n)` if at least `n` bytes can be read without blocking, or `None` if that is
+    /// unknown.
+    fn available_bytes(&self) -> Option<usize>;
+}
+
+/// Returns how many bytes `reader` can deliver without blocking, if known.
+pub fn available_bytes<R: AvailableBytes>(reader: &R) -> Option<usize> {
+    reader.available_bytes()
+}
+
+impl<'a> AvailableBytes for &'a [u8] {
+    fn available_bytes(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T: AsRef<[u8]>> AvailableBytes for Cursor<T> {
+    fn available_bytes(&self) -> Option<usize> {
+        let len = self.get_ref().as_ref().len() as u64;
+        Some(len.saturating_sub(self.position()) as usize)
+    }
+}
@@ -0,0 +1,94 @@
+//! A future that copies an exact number of bytes from a reader to a writer.
+
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Poll, Waker};
+
+use futures_core::future::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use limited_reader::LimitedReader;
+
+const BUF_SIZE: usize = 2048;
+
+/// Create a future that copies exactly `n` bytes from `reader` to `writer`, and then flushes the
+/// writer.
+///
+/// The future resolves to `n`, or to an error of kind `UnexpectedEof` if the reader ran out of
+/// data early. No more than `n` bytes are read from the reader.
+pub fn copy_exact<'a, R, W>(reader: &'a mut R, writer: &'a mut W, n: usize) -> CopyExact<'a, R, W>
+    where R: AsyncRead,
+          W: AsyncWrite
+{
+    CopyExact {
+        reader: LimitedReader::new(reader, n),
+        writer,
+        n,
+        read: 0,
+        buf: Box::new([0; BUF_SIZE]),
+        pos: 0,
+        cap: 0,
+    }
+}
+
+/// Future returned by `copy_exact`.
+pub struct CopyExact<'a, R: 'a, W: 'a> {
+    reader: LimitedReader<&'a mut R>,
+    writer: &'a mut W,
+    n: usize,
+    read: usize,
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+}
+
+impl<'a, R, W> Future for CopyExact<'a, R, W>
+    where R: AsyncRead,
+          W: AsyncWrite
+{
+    type Output = Result<usize, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, wk: &Waker) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        loop {
+            if this.pos == this.cap {
+                if this.read == this.n {
+                    return match this.writer.poll_flush(wk) {
+                        Poll::Ready(Ok(())) => Poll::Ready(Ok(this.n)),
+                        Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                        Poll::Pending => Poll::Pending,
+                    };
+                }
+
+                match this.reader.poll_read(wk, &mut this.buf) {
+                    Poll::Ready(Ok(0)) => {
+                        return Poll::Ready(Err(Error::new(ErrorKind::UnexpectedEof,
+                                                          "reader ended before copying all bytes")))
+                    }
+                    Poll::Ready(Ok(read)) => {
+                        this.read += read;
+                        this.pos = 0;
+                        this.cap = read;
+                    }
+                    Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            while this.pos < this.cap {
+                match this.writer.poll_write(wk, &this.buf[this.pos..this.cap]) {
+                    Poll::Ready(Ok(0)) => {
+                        return Poll::Ready(Err(Error::new(ErrorKind::WriteZero,
+                                                          "writer accepted zero bytes")))
+                    }
+                    Poll::Ready(Ok(written)) => this.pos += written,
+                    Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! A future that reads and discards all bytes from a reader.
+
+use std::cmp::min;
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Poll, Waker};
+
+use futures_core::future::Future;
+use futures_io::AsyncRead;
+
+// The size of the stack buffer bytes are read into, also the default and maximum read size.
+const BUF_SIZE: usize = 1024;
+
+/// Create a future that reads from `reader` until EOF, discards all bytes, and resolves to the
+/// number of bytes that were read.
+///
+/// Errors of kind `Interrupted` are retried.
+pub fn drain_all<R: AsyncRead>(reader: &mut R) -> DrainAll<'_, R> {
+    drain_all_with_buf_size(reader, BUF_SIZE)
+}
+
+/// Like `drain_all`, but reads up to `buf_size` bytes at a time rather than the default of 1024.
+///
+/// Bytes are read into a buffer on the stack of `poll`, so `buf_size` is capped at 1024: larger
+/// values read 1024 bytes at a time.
+///
+/// # Panics
+///
+/// Panics if `buf_size` is zero.
+pub fn drain_all_with_buf_size<R: AsyncRead>(reader: &mut R, buf_size: usize) -> DrainAll<'_, R> {
+    assert!(buf_size > 0, "buffer size must be greater than zero");
+    DrainAll {
+        reader,
+        buf_size: min(buf_size, BUF_SIZE),
+        drained: 0,
+    }
+}
+
+/// Future returned by `drain_all` and `drain_all_with_buf_size`.
+///
+/// Does not allocate, bytes are read into a fixed-size buffer on the stack and then discarded.
+#[derive(Debug)]
+pub struct DrainAll<'a, R: 'a> {
+    reader: &'a mut R,
+    buf_size: usize,
+    drained: usize,
+}
+
+impl<'a, R: AsyncRead> Future for DrainAll<'a, R> {
+    type Output = Result<usize, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, wk: &Waker) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut buf = [0; BUF_SIZE];
+
+        loop {
+            match this.reader.poll_read(wk, &mut buf[..this.buf_size]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(this.drained)),
+                Poll::Ready(Ok(read)) => this.drained += read,
+                Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
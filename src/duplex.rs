@@ -1,20 +1,34 @@
 use std::task::{Poll, Waker};
 use std::io::Error;
+use std::pin::Pin;
 
+use futures_core::future::Future;
 use futures_io::{AsyncRead, AsyncWrite};
 
+use available_bytes::AvailableBytes;
+use limited_reader::LimitedReader;
+use limited_writer::LimitedWriter;
+use partial::IsEof;
+
 /// Implements both AsyncRead and AsyncWrite by delegating to an AsyncRead
 /// and an AsyncWrite, taking ownership of both.
+///
+/// After `close_both` has completed, all reads signal EOF without polling the reader.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Duplex<R, W> {
     r: R,
     w: W,
+    read_closed: bool,
 }
 
 impl<R, W> Duplex<R, W> {
     /// Takes ownership of a reader and a writer and creates a new `Duplex`.
     pub fn new(r: R, w: W) -> Duplex<R, W> {
-        Duplex { r, w }
+        Duplex {
+            r,
+            w,
+            read_closed: false,
+        }
     }
 
     /// Gets a reference to the underlying reader.
@@ -41,14 +55,106 @@ impl<R, W> Duplex<R, W> {
     pub fn into_inner(self) -> (R, W) {
         (self.r, self.w)
     }
+
+    /// Creates a new `Duplex` by applying `f` to the reader, keeping the writer. If the read half
+    /// has been closed via `close_both`, it stays closed.
+    pub fn map_reader<F, R2>(self, f: F) -> Duplex<R2, W>
+        where F: FnOnce(R) -> R2
+    {
+        Duplex {
+            r: f(self.r),
+            w: self.w,
+            read_closed: self.read_closed,
+        }
+    }
+
+    /// Creates a new `Duplex` by applying `f` to the writer, keeping the reader. If the read half
+    /// has been closed via `close_both`, it stays closed.
+    pub fn map_writer<F, W2>(self, f: F) -> Duplex<R, W2>
+        where F: FnOnce(W) -> W2
+    {
+        Duplex {
+            r: self.r,
+            w: f(self.w),
+            read_closed: self.read_closed,
+        }
+    }
+
+    /// Create a future that closes the writer and then marks the read half as closed, so that
+    /// all further reads signal EOF. This models shutting down both directions of a connection.
+    pub fn close_both(&mut self) -> CloseBoth<R, W> {
+        CloseBoth { duplex: self }
+    }
+}
+
+/// Future returned by `Duplex::close_both`.
+#[derive(Debug)]
+pub struct CloseBoth<'a, R: 'a, W: 'a> {
+    duplex: &'a mut Duplex<R, W>,
+}
+
+impl<'a, R, W: AsyncWrite> Future for CloseBoth<'a, R, W> {
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, wk: &Waker) -> Poll<Self::Output> {
+        match self.duplex.w.poll_close(wk) {
+            Poll::Ready(Ok(())) => {
+                self.duplex.read_closed = true;
+                Poll::Ready(Ok(()))
+            }
+            other => other,
+        }
+    }
+}
+
+/// A `Duplex` of boxed trait objects, for when the reader and writer types are only known at
+/// runtime.
+pub type BoxDuplex = Duplex<Box<AsyncRead + Send>, Box<AsyncWrite + Send>>;
+
+/// Boxes a reader and a writer and creates a new `BoxDuplex` from them.
+pub fn box_duplex<R, W>(r: R, w: W) -> BoxDuplex
+    where R: AsyncRead + Send + 'static,
+          W: AsyncWrite + Send + 'static
+{
+    Duplex::new(Box::new(r), Box::new(w))
+}
+
+/// Consumes a `Duplex` and returns its halves, limiting the reader to `read_limit` bytes and the
+/// writer to `write_limit` bytes.
+pub fn split_limited<R, W>(duplex: Duplex<R, W>,
+                           read_limit: usize,
+                           write_limit: usize)
+                           -> (LimitedReader<R>, LimitedWriter<W>) {
+    let (r, w) = duplex.into_inner();
+    (LimitedReader::new(r, read_limit), LimitedWriter::new(w, write_limit))
 }
 
 impl<R: AsyncRead, W> AsyncRead for Duplex<R, W> {
     fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        if self.read_closed {
+            return Poll::Ready(Ok(0));
+        }
+
         self.r.poll_read(wk, buf)
     }
 }
 
+impl<R: IsEof, W> IsEof for Duplex<R, W> {
+    fn is_eof(&self) -> bool {
+        self.read_closed || self.r.is_eof()
+    }
+}
+
+impl<R: AvailableBytes, W> AvailableBytes for Duplex<R, W> {
+    fn available_bytes(&self) -> Option<usize> {
+        if self.read_closed {
+            Some(0)
+        } else {
+            self.r.available_bytes()
+        }
+    }
+}
+
 impl<R, W: AsyncWrite> AsyncWrite for Duplex<R, W> {
     fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
         self.w.poll_write(wk, buf)
@@ -62,3 +168,82 @@ impl<R, W: AsyncWrite> AsyncWrite for Duplex<R, W> {
         self.w.poll_close(wk)
     }
 }
+
+/// Wraps a value that is both an AsyncRead and an AsyncWrite, and counts how many bytes are read
+/// from and written to it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CountingDuplex<D> {
+    inner: D,
+    read_count: u64,
+    write_count: u64,
+}
+
+impl<D> CountingDuplex<D> {
+    /// Takes ownership of a duplex and creates a new `CountingDuplex` with both counters at zero.
+    pub fn new(inner: D) -> CountingDuplex<D> {
+        CountingDuplex {
+            inner,
+            read_count: 0,
+            write_count: 0,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn reads(&self) -> u64 {
+        self.read_count
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn writes(&self) -> u64 {
+        self.write_count
+    }
+
+    /// Resets both counters to zero.
+    pub fn reset_counters(&mut self) {
+        self.read_count = 0;
+        self.write_count = 0;
+    }
+
+    /// Gets a reference to the underlying duplex.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying duplex.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Unwraps this `CountingDuplex`, returning the underlying duplex.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: AsyncRead> AsyncRead for CountingDuplex<D> {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let res = self.inner.poll_read(wk, buf);
+        if let Poll::Ready(Ok(read)) = res {
+            self.read_count += read as u64;
+        }
+        res
+    }
+}
+
+impl<D: AsyncWrite> AsyncWrite for CountingDuplex<D> {
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let res = self.inner.poll_write(wk, buf);
+        if let Poll::Ready(Ok(written)) = res {
+            self.write_count += written as u64;
+        }
+        res
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_flush(wk)
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_close(wk)
+    }
+}
@@ -0,0 +1,59 @@
+//! A reader serving the bytes a future resolves to.
+
+use std::cmp::min;
+use std::io::Error;
+use std::pin::Pin;
+use std::task::{Poll, Waker};
+
+use futures_core::future::Future;
+use futures_io::AsyncRead;
+
+/// Implements AsyncRead by polling a future that resolves to a `Vec<u8>`, and then serving the
+/// bytes of that vector.
+///
+/// Reads return `Pending` while the future is pending. If the future resolves to an error, that
+/// error is returned by the read, and all further reads signal EOF.
+///
+/// The future is pinned on the heap, so it does not need to be `Unpin`, e.g. it can be an `async`
+/// block.
+#[derive(Debug)]
+pub struct FutureReader<F> {
+    fut: Option<Pin<Box<F>>>,
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl<F> FutureReader<F>
+    where F: Future<Output = Result<Vec<u8>, Error>>
+{
+    /// Create a new `FutureReader`, serving the data the given future resolves to.
+    pub fn new(fut: F) -> FutureReader<F> {
+        FutureReader {
+            fut: Some(Box::pin(fut)),
+            data: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<F> AsyncRead for FutureReader<F>
+    where F: Future<Output = Result<Vec<u8>, Error>>
+{
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        if let Some(mut fut) = self.fut.take() {
+            match fut.as_mut().poll(wk) {
+                Poll::Ready(Ok(data)) => self.data = data,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => {
+                    self.fut = Some(fut);
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        let len = min(self.data.len() - self.position, buf.len());
+        buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+        self.position += len;
+        Poll::Ready(Ok(len))
+    }
+}
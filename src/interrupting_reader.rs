@@ -0,0 +1,56 @@
+//! A wrapper around a reader that periodically fails with `ErrorKind::Interrupted`.
+
+use std::io::{Error, ErrorKind};
+use std::task::{Poll, Waker};
+
+use futures_io::AsyncRead;
+
+/// Wraps a reader and, after every `interrupt_every` polls that are delegated to the inner reader,
+/// returns an error of kind `Interrupted` instead of delegating.
+///
+/// With `interrupt_every = 1`, every other poll is interrupted. An `interrupt_every` of zero
+/// disables interruptions.
+#[derive(Debug)]
+pub struct InterruptingReader<R> {
+    inner: R,
+    interrupt_every: usize,
+    delegated: usize,
+}
+
+impl<R> InterruptingReader<R> {
+    /// Create a new `InterruptingReader`, wrapping the given reader.
+    pub fn new(inner: R, interrupt_every: usize) -> InterruptingReader<R> {
+        InterruptingReader {
+            inner,
+            interrupt_every,
+            delegated: 0,
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes this `InterruptingReader`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for InterruptingReader<R> {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        if self.interrupt_every != 0 && self.delegated >= self.interrupt_every {
+            self.delegated = 0;
+            return Poll::Ready(Err(Error::new(ErrorKind::Interrupted, "InterruptingReader")));
+        }
+
+        self.delegated += 1;
+        self.inner.poll_read(wk, buf)
+    }
+}
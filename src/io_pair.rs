@@ -0,0 +1,161 @@
+//! An in-memory, bidirectional channel between two endpoints.
+
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+/// Creates pairs of connected `IoPairEnd`s.
+#[derive(Debug)]
+pub struct IoPair;
+
+impl IoPair {
+    /// Create two connected endpoints. Bytes written to one of them can be read from the other.
+    ///
+    /// Each direction buffers at most `buf_size` bytes, writing to a full buffer returns `Pending`
+    /// until the other end reads. A `buf_size` of zero results in endpoints that can never
+    /// transfer any data.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(buf_size: usize) -> (IoPairEnd, IoPairEnd) {
+        let a_to_b = Arc::new(Mutex::new(Channel::new(buf_size)));
+        let b_to_a = Arc::new(Mutex::new(Channel::new(buf_size)));
+
+        (IoPairEnd {
+             incoming: b_to_a.clone(),
+             outgoing: a_to_b.clone(),
+         },
+         IoPairEnd {
+             incoming: a_to_b,
+             outgoing: b_to_a,
+         })
+    }
+}
+
+#[derive(Debug)]
+struct Channel {
+    buf: VecDeque<u8>,
+    capacity: usize,
+    closed: bool,
+    reader_dropped: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Channel {
+    fn new(capacity: usize) -> Channel {
+        Channel {
+            buf: VecDeque::with_capacity(capacity),
+            capacity,
+            closed: false,
+            reader_dropped: false,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(read_waker) = self.read_waker.take() {
+            read_waker.wake();
+        }
+    }
+
+    fn drop_reader(&mut self) {
+        self.reader_dropped = true;
+        self.buf.clear();
+        if let Some(write_waker) = self.write_waker.take() {
+            write_waker.wake();
+        }
+    }
+}
+
+/// One end of an `IoPair`.
+///
+/// Closing or dropping an end signals EOF to the other end once it has read all remaining bytes.
+/// Dropping an end additionally makes all further writes of the other end fail with an error of
+/// kind `BrokenPipe`.
+#[derive(Debug)]
+pub struct IoPairEnd {
+    incoming: Arc<Mutex<Channel>>,
+    outgoing: Arc<Mutex<Channel>>,
+}
+
+impl IoPairEnd {
+    /// Returns how many bytes can currently be written before the other end needs to read.
+    pub fn capacity_left(&self) -> usize {
+        let outgoing = self.outgoing.lock().unwrap();
+        outgoing.capacity - outgoing.buf.len()
+    }
+}
+
+impl Drop for IoPairEnd {
+    fn drop(&mut self) {
+        if let Ok(mut outgoing) = self.outgoing.lock() {
+            outgoing.close();
+        }
+        if let Ok(mut incoming) = self.incoming.lock() {
+            incoming.drop_reader();
+        }
+    }
+}
+
+impl AsyncRead for IoPairEnd {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let mut incoming = self.incoming.lock().unwrap();
+
+        if incoming.buf.is_empty() && !buf.is_empty() {
+            if incoming.closed {
+                return Poll::Ready(Ok(0));
+            }
+            incoming.read_waker = Some(wk.clone());
+            return Poll::Pending;
+        }
+
+        let len = min(incoming.buf.len(), buf.len());
+        for (dst, src) in buf.iter_mut().zip(incoming.buf.drain(..len)) {
+            *dst = src;
+        }
+        if let Some(write_waker) = incoming.write_waker.take() {
+            write_waker.wake();
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for IoPairEnd {
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let mut outgoing = self.outgoing.lock().unwrap();
+
+        if outgoing.closed {
+            return Poll::Ready(Err(Error::new(ErrorKind::BrokenPipe, "write to closed IoPairEnd")));
+        }
+        if outgoing.reader_dropped {
+            return Poll::Ready(Err(Error::new(ErrorKind::BrokenPipe,
+                                              "write to IoPairEnd whose other end was dropped")));
+        }
+
+        let len = min(outgoing.capacity - outgoing.buf.len(), buf.len());
+        if len == 0 && !buf.is_empty() {
+            outgoing.write_waker = Some(wk.clone());
+            return Poll::Pending;
+        }
+
+        outgoing.buf.extend(&buf[..len]);
+        if let Some(read_waker) = outgoing.read_waker.take() {
+            read_waker.wake();
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        self.outgoing.lock().unwrap().close();
+        Poll::Ready(Ok(()))
+    }
+}
@@ -5,12 +5,34 @@
 extern crate futures_core;
 extern crate futures_io;
 #[cfg(feature = "quickcheck")]
+#[cfg_attr(test, macro_use)]
 extern crate quickcheck;
 
-mod duplex;
+#[macro_use]
 mod macros;
+mod duplex;
+mod noop_waker;
+mod sink_writer;
+mod vec_writer;
 pub mod partial;
 pub mod limited_reader;
+pub mod limited_writer;
+pub mod limiter;
+pub mod available_bytes;
+pub mod copy_exact;
+pub mod drain_all;
+pub mod future_reader;
+pub mod interrupting_reader;
+pub mod io_pair;
+pub mod mock_duplex;
+pub mod op_encoding;
+pub mod overflowing_reader;
+pub mod read_until_eof;
+pub mod sync_to_async;
+pub mod timeout_reader;
+pub mod transform;
 
 pub use duplex::*;
 pub use macros::*;
+pub use sink_writer::*;
+pub use vec_writer::*;
@@ -1,31 +1,172 @@
 //! A wrapper around a reader that limits how many bytes can be read from it.
 
 use std::cmp::min;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Poll, Waker};
 
-use futures_io::AsyncRead;
+use futures_io::{AsyncRead, AsyncWrite, IoVec};
+
+use limiter::{consume, replenish};
+
+/// What a `LimitedReader` does when reading after its limit has been reached.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LimitBehavior {
+    /// Signal EOF by returning `Ok(Ready(0))`.
+    Eof,
+    /// Return an error of the given kind.
+    Error(ErrorKind),
+}
 
 /// Wraps a reader and limits the number of bytes that can be read from it. Once the limit has been
-/// reached, further calls to poll_read will return `Ok(Ready(0))`.
+/// reached, further calls to poll_read will return `Ok(Ready(0))`, or an error if configured via
+/// `LimitedReader::with_behavior`.
+///
+/// If the wrapped value is also a writer, writes are passed through without any limit.
 pub struct LimitedReader<R> {
     inner: R,
-    remaining: usize,
+    remaining: Arc<AtomicUsize>,
+    on_exceeded: LimitBehavior,
 }
 
 impl<R> LimitedReader<R> {
     /// Create a new `LimitedReader`, wrapping the given reader.
     pub fn new(inner: R, limit: usize) -> LimitedReader<R> {
+        LimitedReader::with_budget(inner, Arc::new(AtomicUsize::new(limit)))
+    }
+
+    /// Create a new `LimitedReader`, wrapping the given reader and behaving according to
+    /// `on_exceeded` once the limit has been reached.
+    pub fn with_behavior(inner: R, limit: usize, on_exceeded: LimitBehavior) -> LimitedReader<R> {
+        LimitedReader {
+            inner,
+            remaining: Arc::new(AtomicUsize::new(limit)),
+            on_exceeded,
+        }
+    }
+
+    pub(crate) fn with_budget(inner: R, remaining: Arc<AtomicUsize>) -> LimitedReader<R> {
         LimitedReader {
-            inner: inner,
-            remaining: limit,
+            inner,
+            remaining,
+            on_exceeded: LimitBehavior::Eof,
         }
     }
+
+    /// Create a new `LimitedReaderDyn` with an initial limit of `initial` bytes, and a
+    /// `LimitQuota` through which the limit can be raised later, e.g. from another task.
+    pub fn with_shared_limit(inner: R, initial: usize) -> (LimitedReaderDyn<R>, LimitQuota) {
+        let quota = LimitQuota(Arc::new(Quota {
+            remaining: AtomicUsize::new(initial),
+            waker: Mutex::new(None),
+        }));
+        (LimitedReaderDyn {
+             inner,
+             quota: quota.clone(),
+         },
+         quota)
+    }
 }
 
 impl<R: AsyncRead> AsyncRead for LimitedReader<R> {
     fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
-        let upper = min(self.remaining, buf.len());
-        self.inner.poll_read(wk, &mut buf[..upper])
+        let remaining = self.remaining.load(Ordering::SeqCst);
+        if remaining == 0 && !buf.is_empty() {
+            return Poll::Ready(match self.on_exceeded {
+                LimitBehavior::Eof => Ok(0),
+                LimitBehavior::Error(kind) => Err(Error::new(kind, "limit exceeded")),
+            });
+        }
+
+        let upper = min(remaining, buf.len());
+        if upper == 0 {
+            return Poll::Ready(Ok(0));
+        }
+
+        let res = self.inner.poll_read(wk, &mut buf[..upper]);
+        if let Poll::Ready(Ok(read)) = res {
+            consume(&self.remaining, read);
+        }
+        res
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for LimitedReader<W> {
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        self.inner.poll_write(wk, buf)
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_flush(wk)
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_close(wk)
+    }
+
+    fn poll_vectored_write(&mut self, wk: &Waker, vec: &[&IoVec]) -> Poll<Result<usize, Error>> {
+        self.inner.poll_vectored_write(wk, vec)
+    }
+}
+
+#[derive(Debug)]
+struct Quota {
+    remaining: AtomicUsize,
+    waker: Mutex<Option<Waker>>,
+}
+
+/// A handle for raising the limit of a `LimitedReaderDyn`.
+#[derive(Debug, Clone)]
+pub struct LimitQuota(Arc<Quota>);
+
+impl LimitQuota {
+    /// Allow `n` more bytes to be read, waking the reader if it is waiting for quota.
+    pub fn add_quota(&self, n: usize) {
+        replenish(&self.0.remaining, n);
+        if let Some(waker) = self.0.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    /// Returns how many bytes may currently be read.
+    pub fn remaining(&self) -> usize {
+        self.0.remaining.load(Ordering::SeqCst)
+    }
+}
+
+/// Wraps a reader and limits the number of bytes that can be read from it, where the limit can be
+/// raised via a `LimitQuota`. While the limit is exhausted, poll_read returns `Pending` until more
+/// quota is added.
+///
+/// Obtained via `LimitedReader::with_shared_limit`.
+#[derive(Debug)]
+pub struct LimitedReaderDyn<R> {
+    inner: R,
+    quota: LimitQuota,
+}
+
+impl<R: AsyncRead> AsyncRead for LimitedReaderDyn<R> {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let quota = &self.quota.0;
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if quota.remaining.load(Ordering::SeqCst) == 0 {
+            *quota.waker.lock().unwrap() = Some(wk.clone());
+            // Quota might have been added before the waker was stored.
+            if quota.remaining.load(Ordering::SeqCst) == 0 {
+                return Poll::Pending;
+            }
+        }
+
+        let upper = min(quota.remaining.load(Ordering::SeqCst), buf.len());
+        let res = self.inner.poll_read(wk, &mut buf[..upper]);
+        if let Poll::Ready(Ok(read)) = res {
+            consume(&quota.remaining, read);
+        }
+        res
     }
 }
@@ -0,0 +1,75 @@
+//! A wrapper around a writer that limits how many bytes can be written to it.
+
+use std::cmp::min;
+use std::io::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Poll, Waker};
+
+use futures_io::AsyncWrite;
+
+use limiter::{consume, replenish};
+
+/// Wraps a writer and limits the number of bytes that can be written to it. Once the limit has
+/// been reached, further calls to poll_write will return `Ok(Ready(0))`.
+///
+/// Flushing and closing are passed through to the inner writer unmodified.
+pub struct LimitedWriter<W> {
+    inner: W,
+    remaining: Arc<AtomicUsize>,
+    refill: usize,
+}
+
+impl<W> LimitedWriter<W> {
+    /// Create a new `LimitedWriter`, wrapping the given writer.
+    pub fn new(inner: W, limit: usize) -> LimitedWriter<W> {
+        LimitedWriter::with_budget(inner, Arc::new(AtomicUsize::new(limit)))
+    }
+
+    /// Create a new `LimitedWriter` whose limit is raised by `refill` bytes whenever a call to
+    /// `poll_flush` completes successfully.
+    ///
+    /// This models flow control where a flush acknowledges the written data and grants more quota.
+    pub fn with_flush_reset(inner: W, limit: usize, refill: usize) -> LimitedWriter<W> {
+        LimitedWriter {
+            inner,
+            remaining: Arc::new(AtomicUsize::new(limit)),
+            refill,
+        }
+    }
+
+    pub(crate) fn with_budget(inner: W, remaining: Arc<AtomicUsize>) -> LimitedWriter<W> {
+        LimitedWriter {
+            inner,
+            remaining,
+            refill: 0,
+        }
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for LimitedWriter<W> {
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let upper = min(self.remaining.load(Ordering::SeqCst), buf.len());
+        if upper == 0 {
+            return Poll::Ready(Ok(0));
+        }
+
+        let res = self.inner.poll_write(wk, &buf[..upper]);
+        if let Poll::Ready(Ok(written)) = res {
+            consume(&self.remaining, written);
+        }
+        res
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        let res = self.inner.poll_flush(wk);
+        if let Poll::Ready(Ok(())) = res {
+            replenish(&self.remaining, self.refill);
+        }
+        res
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_close(wk)
+    }
+}
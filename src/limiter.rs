@@ -0,0 +1,62 @@
+//! A byte budget shared between a `LimitedReader` and a `LimitedWriter`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use limited_reader::LimitedReader;
+use limited_writer::LimitedWriter;
+
+/// A byte budget that can be shared between any number of `LimitedReader`s and `LimitedWriter`s.
+///
+/// Every byte read or written through one of them is deducted from the shared budget. Once it
+/// has been used up, all of them behave as if they had reached their limit.
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    budget: Arc<AtomicUsize>,
+}
+
+impl Limiter {
+    /// Create a new `Limiter` with a total budget of `total` bytes.
+    pub fn new(total: usize) -> Limiter {
+        Limiter { budget: Arc::new(AtomicUsize::new(total)) }
+    }
+
+    /// Returns how many bytes of the budget are left.
+    pub fn remaining(&self) -> usize {
+        self.budget.load(Ordering::SeqCst)
+    }
+
+    /// Wrap the given reader in a `LimitedReader` that draws from this budget.
+    pub fn reader<R>(&self, r: R) -> LimitedReader<R> {
+        LimitedReader::with_budget(r, self.budget.clone())
+    }
+
+    /// Wrap the given writer in a `LimitedWriter` that draws from this budget.
+    pub fn writer<W>(&self, w: W) -> LimitedWriter<W> {
+        LimitedWriter::with_budget(w, self.budget.clone())
+    }
+}
+
+// Deduct `n` bytes from the budget, saturating at zero.
+pub(crate) fn consume(budget: &AtomicUsize, n: usize) {
+    let mut current = budget.load(Ordering::SeqCst);
+    loop {
+        let new = current.saturating_sub(n);
+        match budget.compare_exchange_weak(current, new, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+// Add `n` bytes to the budget, saturating at `usize::MAX`.
+pub(crate) fn replenish(budget: &AtomicUsize, n: usize) {
+    let mut current = budget.load(Ordering::SeqCst);
+    loop {
+        let new = current.saturating_add(n);
+        match budget.compare_exchange_weak(current, new, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
@@ -42,3 +42,66 @@ macro_rules! write_nz {
         }
     )
 }
+
+/// A variant of `read_nz!` for use in `async fn`s: awaits the expression, propagates errors via
+/// `?`, and emits a `futures_io::Error` of kind `UnexpectedEof` with the given message if it
+/// evaluates to 0.
+#[macro_export]
+macro_rules! read_nz_async {
+    ($e:expr, $msg:expr) => (
+        {
+            let read = await!($e)?;
+            if read == 0 {
+                return Err(::futures_io::Error::new(::futures_io::ErrorKind::UnexpectedEof, $msg).into());
+            }
+            read
+        }
+    )
+}
+
+/// A variant of `write_nz!` for use in `async fn`s: awaits the expression, propagates errors via
+/// `?`, and emits a `futures_io::Error` of kind `WriteZero` with the given message if it evaluates
+/// to 0.
+#[macro_export]
+macro_rules! write_nz_async {
+    ($e:expr, $msg:expr) => (
+        {
+            let written = await!($e)?;
+            if written == 0 {
+                return Err(::futures_io::Error::new(::futures_io::ErrorKind::WriteZero, $msg).into());
+            }
+            written
+        }
+    )
+}
+
+/// Drives a `PartialRead` to completion via `partial::simulate_read`, and asserts that the bytes
+/// read equal the expected ones.
+///
+/// On failure, the panic message includes both byte sequences, the offset of the first difference,
+/// and the `PartialReadStats` describing the ops that were performed.
+#[macro_export]
+macro_rules! assert_partial_read_eq {
+    ($reader:expr, $expected:expr) => (
+        {
+            let reader = &mut $reader;
+            let actual = match $crate::partial::simulate_read(reader) {
+                Ok(actual) => actual,
+                Err(e) => panic!("partial read failed: {}\n  stats: {:?}", e, reader.stats()),
+            };
+            let expected: &[u8] = &$expected[..];
+
+            if &actual[..] != expected {
+                let first_difference = actual.iter()
+                    .zip(expected.iter())
+                    .position(|(a, e)| a != e)
+                    .unwrap_or_else(|| ::std::cmp::min(actual.len(), expected.len()));
+                panic!("partial read mismatch at byte {}\n  expected: {:?}\n  actual: {:?}\n  stats: {:?}",
+                       first_difference,
+                       expected,
+                       actual,
+                       reader.stats());
+            }
+        }
+    )
+}
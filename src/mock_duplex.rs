@@ -0,0 +1,299 @@
+//! A scripted duplex for testing code against exact sequences of read results.
+
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::task::{Poll, Waker};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use {Duplex, VecWriter};
+use available_bytes::AvailableBytes;
+use partial::IsEof;
+
+/// The result of a single read from a `MockReader`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadResponse {
+    /// Read these bytes. If they do not fit into the buffer, the remaining bytes are kept as the
+    /// next response.
+    Data(Vec<u8>),
+    /// Wake the current task and return `Pending`.
+    Pending,
+    /// Return an error of the given kind.
+    Error(ErrorKind),
+    /// Signal EOF by reading zero bytes.
+    Eof,
+}
+
+/// Implements AsyncRead by popping one `ReadResponse` per call to `poll_read`.
+///
+/// Once all responses have been used up, all further reads signal EOF.
+#[derive(Debug, Clone, Default)]
+pub struct MockReader {
+    responses: VecDeque<ReadResponse>,
+    eof: bool,
+}
+
+impl MockReader {
+    /// Create a new `MockReader` that responds to reads with the given responses, in order.
+    pub fn new(responses: Vec<ReadResponse>) -> MockReader {
+        MockReader {
+            responses: responses.into(),
+            eof: false,
+        }
+    }
+
+    /// Append a response to use after all previously scripted ones.
+    pub fn push_response(&mut self, response: ReadResponse) {
+        self.responses.push_back(response);
+    }
+
+    /// Returns the responses that have not been used by a read yet.
+    pub fn remaining_responses(&self) -> &VecDeque<ReadResponse> {
+        &self.responses
+    }
+}
+
+impl AsyncRead for MockReader {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let response = self.responses.pop_front();
+        self.eof = response.is_none() || response == Some(ReadResponse::Eof);
+
+        match response {
+            None | Some(ReadResponse::Eof) => Poll::Ready(Ok(0)),
+            Some(ReadResponse::Pending) => {
+                wk.wake();
+                Poll::Pending
+            }
+            Some(ReadResponse::Error(kind)) => Poll::Ready(Err(Error::from(kind))),
+            Some(ReadResponse::Data(mut data)) => {
+                let len = min(data.len(), buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                if len < data.len() {
+                    self.responses.push_front(ReadResponse::Data(data.split_off(len)));
+                }
+                Poll::Ready(Ok(len))
+            }
+        }
+    }
+}
+
+/// A read of zero bytes signals EOF if it used up an `Eof` response, or if there were no
+/// responses left.
+impl IsEof for MockReader {
+    fn is_eof(&self) -> bool {
+        self.eof
+    }
+}
+
+/// Counts the bytes of all `Data` responses up to the first response of another kind.
+impl AvailableBytes for MockReader {
+    fn available_bytes(&self) -> Option<usize> {
+        let mut available = 0;
+        for response in &self.responses {
+            match *response {
+                ReadResponse::Data(ref data) => available += data.len(),
+                _ => break,
+            }
+        }
+        Some(available)
+    }
+}
+
+/// Implements AsyncWrite by recording all written bytes.
+///
+/// Writes always succeed immediately, flushing and closing do nothing. Optionally, each write can
+/// be checked against an expected sequence of chunks, see `expect_write_sequence`.
+#[derive(Debug, Clone, Default)]
+pub struct MockWriter {
+    writer: VecWriter,
+    expected: Option<VecDeque<Vec<u8>>>,
+    matched: usize,
+}
+
+impl MockWriter {
+    /// Create a new `MockWriter` that has not been written to yet.
+    pub fn new() -> MockWriter {
+        MockWriter::default()
+    }
+
+    /// Returns all bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        self.writer.get_ref()
+    }
+
+    /// Expect the following writes to consist of exactly the given chunks, in order.
+    ///
+    /// Each nonempty write is compared to the next expected chunk, and panics if it does not
+    /// match, or if no more chunks are expected.
+    pub fn expect_write_sequence(&mut self, expected: Vec<Vec<u8>>) {
+        self.expected = Some(expected.into());
+        self.matched = 0;
+    }
+
+    /// Panics if some of the chunks passed to `expect_write_sequence` have not been written yet.
+    pub fn assert_sequence_complete(&self) {
+        if let Some(ref expected) = self.expected {
+            if !expected.is_empty() {
+                panic!("MockWriter still expects writes after matching {} chunk(s): {:?}",
+                       self.matched,
+                       expected);
+            }
+        }
+    }
+
+    fn check_write(&mut self, buf: &[u8]) {
+        if let Some(ref mut expected) = self.expected {
+            match expected.pop_front() {
+                Some(ref chunk) if &chunk[..] == buf => self.matched += 1,
+                Some(chunk) => {
+                    panic!("MockWriter write {} mismatch\n  expected: {:?}\n  actual: {:?}",
+                           self.matched,
+                           chunk,
+                           buf)
+                }
+                None => {
+                    panic!("MockWriter got write {} but expected no more writes: {:?}",
+                           self.matched,
+                           buf)
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MockWriter {
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        if !buf.is_empty() {
+            self.check_write(buf);
+        }
+        self.writer.poll_write(wk, buf)
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.writer.poll_flush(wk)
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.writer.poll_close(wk)
+    }
+}
+
+/// A `Duplex` whose halves may each be mocked, providing the convenience methods of
+/// `MockReader` and `MockWriter` for the halves that are.
+///
+/// Obtained via `MockDuplex::with_reader` or `MockDuplex::with_writer`, or as a `MockDuplex` if
+/// both halves are mocked.
+#[derive(Debug, Clone)]
+pub struct HybridDuplex<R, W = MockWriter>(Duplex<R, W>);
+
+/// Implements AsyncRead by popping one `ReadResponse` per call to `poll_read`, and AsyncWrite by
+/// recording all written bytes.
+pub type MockDuplex = HybridDuplex<MockReader, MockWriter>;
+
+impl Default for HybridDuplex<MockReader, MockWriter> {
+    fn default() -> MockDuplex {
+        MockDuplex::with_responses(Vec::new())
+    }
+}
+
+impl HybridDuplex<MockReader, MockWriter> {
+    /// Create a new `MockDuplex` without any scripted read responses.
+    pub fn new() -> MockDuplex {
+        MockDuplex::default()
+    }
+
+    /// Create a new `MockDuplex` that responds to reads with the given responses, in order.
+    pub fn with_responses(responses: Vec<ReadResponse>) -> MockDuplex {
+        HybridDuplex(Duplex::new(MockReader::new(responses), MockWriter::new()))
+    }
+
+    /// Create a new `HybridDuplex` that reads from `r`, and records writes like a `MockDuplex`.
+    pub fn with_reader<R: AsyncRead>(r: R) -> HybridDuplex<R> {
+        HybridDuplex(Duplex::new(r, MockWriter::new()))
+    }
+
+    /// Create a new `HybridDuplex` that writes to `w`, and has no scripted read responses.
+    pub fn with_writer<W: AsyncWrite>(w: W) -> HybridDuplex<MockReader, W> {
+        HybridDuplex(Duplex::new(MockReader::default(), w))
+    }
+}
+
+impl<R, W> HybridDuplex<R, W> {
+    /// Gets a reference to the underlying `Duplex`.
+    pub fn get_ref(&self) -> &Duplex<R, W> {
+        &self.0
+    }
+
+    /// Gets a mutable reference to the underlying `Duplex`.
+    pub fn get_mut(&mut self) -> &mut Duplex<R, W> {
+        &mut self.0
+    }
+
+    /// Consumes this `HybridDuplex`, returning the underlying `Duplex`.
+    pub fn into_inner(self) -> Duplex<R, W> {
+        self.0
+    }
+}
+
+impl<W> HybridDuplex<MockReader, W> {
+    /// Append a response to use after all previously scripted ones.
+    pub fn push_response(&mut self, response: ReadResponse) {
+        self.0.get_reader_mut().push_response(response);
+    }
+
+    /// Returns the responses that have not been used by a read yet.
+    pub fn remaining_responses(&self) -> &VecDeque<ReadResponse> {
+        self.0.get_reader_ref().remaining_responses()
+    }
+}
+
+impl<R> HybridDuplex<R, MockWriter> {
+    /// Returns all bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        self.0.get_writer_ref().written()
+    }
+
+    /// Expect the following writes to consist of exactly the given chunks, in order. See
+    /// `MockWriter::expect_write_sequence`.
+    pub fn expect_write_sequence(&mut self, expected: Vec<Vec<u8>>) {
+        self.0.get_writer_mut().expect_write_sequence(expected);
+    }
+
+    /// Panics if some of the expected chunks have not been written yet.
+    pub fn assert_sequence_complete(&self) {
+        self.0.get_writer_ref().assert_sequence_complete();
+    }
+}
+
+impl<R: AsyncRead, W> AsyncRead for HybridDuplex<R, W> {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        self.0.poll_read(wk, buf)
+    }
+}
+
+impl<R: IsEof, W> IsEof for HybridDuplex<R, W> {
+    fn is_eof(&self) -> bool {
+        self.0.is_eof()
+    }
+}
+
+impl<R: AvailableBytes, W> AvailableBytes for HybridDuplex<R, W> {
+    fn available_bytes(&self) -> Option<usize> {
+        self.0.available_bytes()
+    }
+}
+
+impl<R, W: AsyncWrite> AsyncWrite for HybridDuplex<R, W> {
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        self.0.poll_write(wk, buf)
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.0.poll_flush(wk)
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.0.poll_close(wk)
+    }
+}
@@ -0,0 +1,23 @@
+use std::ptr;
+use std::task::{RawWaker, RawWakerVTable, Waker};
+
+unsafe fn noop_clone(_: *const ()) -> RawWaker {
+    noop_raw_waker()
+}
+
+unsafe fn noop(_: *const ()) {}
+
+static NOOP_VTABLE: RawWakerVTable = RawWakerVTable {
+    clone: noop_clone,
+    wake: noop,
+    drop: noop,
+};
+
+fn noop_raw_waker() -> RawWaker {
+    RawWaker::new(ptr::null(), &NOOP_VTABLE)
+}
+
+// A waker that does nothing, for driving io operations synchronously.
+pub(crate) fn noop_waker() -> Waker {
+    unsafe { Waker::new_unchecked(noop_raw_waker()) }
+}
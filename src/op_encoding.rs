@@ -0,0 +1,163 @@
+//! A compact binary encoding for sequences of `PartialOp`s, e.g. for storing test cases or fuzzing
+//! corpora.
+//!
+//! Each op starts with a tag byte, followed by its argument (if any) as a big-endian integer:
+//!
+//! - `0x00`: `Unlimited`
+//! - `0x01`: `Pending`
+//! - `0x02 NN`, `0x03 NN NN`, `0x04 NN NN NN NN`, `0x05 NN NN NN NN NN NN NN NN`: `Limited` with
+//!   an 8, 16, 32 or 64 bit argument
+//! - `0x06` to `0x09`: `Skip`, with arguments like `Limited`
+//! - `0x0a` to `0x0d`: `SplitAt`, with arguments like `Limited`
+//! - `0x0e` to `0x11`: `Chain`, with the number of contained ops as an argument like `Limited`,
+//!   followed by the encoded contained ops
+//!
+//! `encode_ops` always picks the smallest width that fits the argument. `decode_ops` rejects
+//! chains nested more than `MAX_CHAIN_DEPTH` levels deep.
+
+use std::cmp::min;
+use std::error::Error;
+use std::fmt;
+
+use partial::PartialOp;
+
+const UNLIMITED: u8 = 0x00;
+const PENDING: u8 = 0x01;
+const LIMITED: u8 = 0x02;
+const SKIP: u8 = 0x06;
+const SPLIT_AT: u8 = 0x0a;
+const CHAIN: u8 = 0x0e;
+
+/// The maximum number of nested `Chain`s accepted by `decode_ops`.
+pub const MAX_CHAIN_DEPTH: usize = 64;
+
+/// Everything that can go wrong when decoding a sequence of `PartialOp`s.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Encountered a tag byte that does not correspond to any op.
+    UnknownTag(u8),
+    /// The input ended in the middle of an op.
+    UnexpectedEnd,
+    /// An argument does not fit into a `usize` on this platform.
+    Overflow,
+    /// `Chain`s are nested more than `MAX_CHAIN_DEPTH` levels deep.
+    TooDeep,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnknownTag(tag) => write!(f, "unknown op tag {:#04x}", tag),
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::Overflow => write!(f, "op argument does not fit into a usize"),
+            DecodeError::TooDeep => write!(f, "op chains are nested too deeply"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Encode a sequence of ops.
+pub fn encode_ops(ops: &[PartialOp]) -> Vec<u8> {
+    let mut out = Vec::new();
+    // The remaining ops of the chains that are currently being encoded, innermost last.
+    let mut chains = vec![ops.iter()];
+
+    loop {
+        let op = match chains.last_mut().map(Iterator::next) {
+            Some(Some(op)) => op,
+            Some(None) => {
+                chains.pop();
+                continue;
+            }
+            None => return out,
+        };
+
+        match *op {
+            PartialOp::Unlimited => out.push(UNLIMITED),
+            PartialOp::Pending => out.push(PENDING),
+            PartialOp::Limited(n) => encode_arg(&mut out, LIMITED, n),
+            PartialOp::Skip(n) => encode_arg(&mut out, SKIP, n),
+            PartialOp::SplitAt(n) => encode_arg(&mut out, SPLIT_AT, n),
+            PartialOp::Chain(ref ops) => {
+                encode_arg(&mut out, CHAIN, ops.len());
+                chains.push(ops.iter());
+            }
+        }
+    }
+}
+
+fn encode_arg(out: &mut Vec<u8>, base_tag: u8, n: usize) {
+    let n = n as u64;
+    let width = if n <= 0xff {
+        0
+    } else if n <= 0xffff {
+        1
+    } else if n <= 0xffff_ffff {
+        2
+    } else {
+        3
+    };
+
+    out.push(base_tag + width);
+    let len = 1 << width;
+    for i in (0..len).rev() {
+        out.push((n >> (8 * i)) as u8);
+    }
+}
+
+/// Decode a sequence of ops.
+pub fn decode_ops(mut bytes: &[u8]) -> Result<Vec<PartialOp>, DecodeError> {
+    let mut ops = Vec::new();
+    while !bytes.is_empty() {
+        ops.push(decode_op(&mut bytes, 0)?);
+    }
+    Ok(ops)
+}
+
+// Decode a single op, which is nested in `depth` chains.
+fn decode_op(bytes: &mut &[u8], depth: usize) -> Result<PartialOp, DecodeError> {
+    let (&tag, rest) = bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+    *bytes = rest;
+
+    Ok(match tag {
+        UNLIMITED => PartialOp::Unlimited,
+        PENDING => PartialOp::Pending,
+        LIMITED..=0x05 => PartialOp::Limited(decode_arg(bytes, tag - LIMITED)?),
+        SKIP..=0x09 => PartialOp::Skip(decode_arg(bytes, tag - SKIP)?),
+        SPLIT_AT..=0x0d => PartialOp::SplitAt(decode_arg(bytes, tag - SPLIT_AT)?),
+        CHAIN..=0x11 => {
+            if depth == MAX_CHAIN_DEPTH {
+                return Err(DecodeError::TooDeep);
+            }
+
+            let len = decode_arg(bytes, tag - CHAIN)?;
+            // Every op takes at least one byte, which bounds the allocation for bogus lengths.
+            let mut ops = Vec::with_capacity(min(len, bytes.len()));
+            for _ in 0..len {
+                ops.push(decode_op(bytes, depth + 1)?);
+            }
+            PartialOp::Chain(ops)
+        }
+        _ => return Err(DecodeError::UnknownTag(tag)),
+    })
+}
+
+fn decode_arg(bytes: &mut &[u8], width: u8) -> Result<usize, DecodeError> {
+    let len = 1 << width;
+    if bytes.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+
+    let mut n: u64 = 0;
+    for &byte in &bytes[..len] {
+        n = (n << 8) | u64::from(byte);
+    }
+    *bytes = &bytes[len..];
+
+    if n > usize::max_value() as u64 {
+        Err(DecodeError::Overflow)
+    } else {
+        Ok(n as usize)
+    }
+}
@@ -0,0 +1,57 @@
+//! A reader that endlessly cycles through a buffer of data.
+
+use std::cmp::min;
+use std::io::Error;
+use std::task::{Poll, Waker};
+
+use futures_io::AsyncRead;
+
+/// Reads from a `Vec<u8>`, wrapping back to the start of the data whenever its end is reached.
+///
+/// An `OverflowingReader` never signals EOF, unless the data is empty, in which case every read
+/// returns `Ok(Ready(0))`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverflowingReader {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl OverflowingReader {
+    /// Create a new `OverflowingReader`, cycling through the given data.
+    pub fn new(data: Vec<u8>) -> OverflowingReader {
+        OverflowingReader { data, position: 0 }
+    }
+
+    /// Returns the offset into the data at which the next read will start.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Gets a reference to the underlying data.
+    pub fn get_ref(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes this `OverflowingReader`, returning the underlying data.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl AsyncRead for OverflowingReader {
+    fn poll_read(&mut self, _: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        if self.data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let mut read = 0;
+        while read < buf.len() {
+            let len = min(buf.len() - read, self.data.len() - self.position);
+            buf[read..read + len].copy_from_slice(&self.data[self.position..self.position + len]);
+            read += len;
+            self.position = (self.position + len) % self.data.len();
+        }
+
+        Poll::Ready(Ok(read))
+    }
+}
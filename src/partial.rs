@@ -1,14 +1,23 @@
 //! Helpers to test partial and `Pending` io operations.
 //!
 //! Inspired by (and bluntly stealing from) the [partial-io](https://crates.io/crates/partial-io) crate.
+//!
+//! The wrappers in this module never pin their fields, so they are `Unpin` regardless of the
+//! wrapped reader or writer and the ops iterator.
 
 use std::task::{Poll, Poll::Pending, Waker};
-use std::io::Error;
-use std::cmp::min;
-use futures_io::{AsyncRead, AsyncWrite, IoVec};
+use std::io::{Error, ErrorKind};
+use std::cmp::{max, min};
+use std::collections::VecDeque;
+use std::iter::{self, Chain, Repeat, Take};
+use std::vec;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use Duplex;
+use noop_waker::noop_waker;
 
 /// The different operations supported by the partial wrappers.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PartialOp {
     /// Perform the io operation as normal.
     Unlimited,
@@ -16,20 +25,345 @@ pub enum PartialOp {
     Limited(usize),
     /// Emit `Ok(Async::Pending)` and reschedule the task.
     Pending,
+    /// Read the given number of bytes from the inner reader and discard them, emitting
+    /// `Ok(Async::Pending)` and rescheduling the task after each read of the inner reader. Reads
+    /// continue to skip without consuming any further ops until all bytes have been discarded. If
+    /// the inner reader is at EOF, emit `Ok(Ready(0))`. Writers treat this like `Unlimited`.
+    Skip(usize),
+    /// Perform the read as normal, but only deliver up to the given number of bytes. The remaining
+    /// bytes are buffered and delivered by the next read(s), without consuming any further ops.
+    /// If no bytes would be delivered (`SplitAt(0)`), emit `Ok(Async::Pending)` and reschedule the
+    /// task instead. Writers treat this like `Limited`.
+    SplitAt(usize),
+    /// Perform the given ops in order, before continuing with the ops that follow. This allows
+    /// writing commonly used sequences of ops inline. The partial wrappers expand these via
+    /// `flatten`.
+    Chain(Vec<PartialOp>),
+}
+
+/// The default op is `Unlimited`: it performs the io operation unmodified, so it is the identity
+/// of the partial wrappers. This matches how they behave once their ops run out.
+impl Default for PartialOp {
+    fn default() -> Self {
+        PartialOp::Unlimited
+    }
+}
+
+/// The ops iterator used by `PartialRead::with_delay` and `PartialWrite::with_delay`: a number of
+/// `PartialOp::Pending`s, followed by infinitely many `PartialOp::Unlimited`s.
+pub type DelayOps = Chain<Take<Repeat<PartialOp>>, Repeat<PartialOp>>;
+
+/// A `PartialRead` whose ops are given as a `Vec`, see `PartialRead::from_vec`.
+pub type SimplePartialRead<R> = PartialRead<R, vec::IntoIter<PartialOp>>;
+
+/// A `PartialWrite` whose ops are given as a `Vec`, see `PartialWrite::from_vec`.
+pub type SimplePartialWrite<W> = PartialWrite<W, vec::IntoIter<PartialOp>>;
+
+fn delay_ops(n_pending: usize) -> DelayOps {
+    iter::repeat(PartialOp::Pending)
+        .take(n_pending)
+        .chain(iter::repeat(PartialOp::Unlimited))
+}
+
+// Perform a write on `writer`, modified according to `op`.
+fn poll_write_op<W: AsyncWrite>(writer: &mut W,
+                                 op: Option<PartialOp>,
+                                 wk: &Waker,
+                                 buf: &[u8])
+                                 -> Poll<Result<usize, Error>> {
+    match op {
+        None |
+        Some(PartialOp::Unlimited) |
+        Some(PartialOp::Skip(_)) => writer.poll_write(wk, buf),
+        Some(PartialOp::Pending) => {
+            wk.wake();
+            Pending
+        }
+        Some(PartialOp::Limited(n)) |
+        Some(PartialOp::SplitAt(n)) => {
+            let len = min(n, buf.len());
+            writer.poll_write(wk, &buf[..len])
+        }
+        Some(PartialOp::Chain(_)) => unreachable!("chains are expanded by `flatten`"),
+    }
+}
+
+// Perform a flush on `writer`, unless `op` is `Pending`.
+fn poll_flush_op<W: AsyncWrite>(writer: &mut W,
+                                op: Option<PartialOp>,
+                                wk: &Waker)
+                                -> Poll<Result<(), Error>> {
+    match op {
+        Some(PartialOp::Pending) => {
+            wk.wake();
+            Pending
+        }
+        _ => writer.poll_flush(wk),
+    }
+}
+
+// Close `writer`, unless `op` is `Pending`.
+fn poll_close_op<W: AsyncWrite>(writer: &mut W,
+                                op: Option<PartialOp>,
+                                wk: &Waker)
+                                -> Poll<Result<(), Error>> {
+    match op {
+        Some(PartialOp::Pending) => {
+            wk.wake();
+            Pending
+        }
+        _ => writer.poll_close(wk),
+    }
+}
+
+/// Statistics about the read operations performed by a `PartialRead`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PartialReadStats {
+    /// Number of polls performed with `PartialOp::Unlimited` (or after the ops ran out).
+    pub unlimited_polls: usize,
+    /// Number of polls performed with a `PartialOp::Limited`.
+    pub limited_polls: usize,
+    /// Number of polls performed with a `PartialOp::Skip`, including the polls that continued an
+    /// unfinished skip.
+    pub skip_polls: usize,
+    /// Number of polls performed with a `PartialOp::SplitAt`, including the polls that delivered
+    /// the buffered remainder of a split read.
+    pub split_polls: usize,
+    /// Number of polls that returned `Pending`, either due to a `PartialOp::Pending` or because
+    /// the inner reader was pending.
+    pub pending_polls: usize,
+    /// Number of polls that returned an error.
+    pub error_polls: usize,
+    /// Total number of bytes delivered by polls with `PartialOp::Unlimited`.
+    pub unlimited_bytes: usize,
+    /// Total number of bytes delivered by polls with a `PartialOp::Limited`.
+    pub limited_bytes: usize,
+    /// The largest number of bytes delivered by a single successful poll.
+    pub max_bytes_per_poll: Option<usize>,
+    /// The smallest number of bytes delivered by a single successful poll. Polls that delivered
+    /// zero bytes (such as the final EOF read) are not taken into account, so this stays `None`
+    /// until some bytes have been read.
+    pub min_bytes_per_poll: Option<usize>,
+}
+
+impl PartialReadStats {
+    fn record(&mut self, op: &Option<PartialOp>, res: &Poll<Result<usize, Error>>) {
+        match *op {
+            None | Some(PartialOp::Unlimited) => self.unlimited_polls += 1,
+            Some(PartialOp::Limited(_)) => self.limited_polls += 1,
+            Some(PartialOp::Skip(_)) => self.skip_polls += 1,
+            Some(PartialOp::SplitAt(_)) => self.split_polls += 1,
+            Some(PartialOp::Pending) |
+            Some(PartialOp::Chain(_)) => {}
+        }
+
+        match *res {
+            Pending => self.pending_polls += 1,
+            Poll::Ready(Err(_)) => self.error_polls += 1,
+            Poll::Ready(Ok(read)) => {
+                match *op {
+                    None | Some(PartialOp::Unlimited) => self.unlimited_bytes += read,
+                    Some(PartialOp::Limited(_)) => self.limited_bytes += read,
+                    _ => {}
+                }
+                if read > 0 {
+                    self.max_bytes_per_poll =
+                        Some(self.max_bytes_per_poll.map_or(read, |m| max(m, read)));
+                    self.min_bytes_per_poll =
+                        Some(self.min_bytes_per_poll.map_or(read, |m| min(m, read)));
+                }
+            }
+        }
+    }
+}
+
+/// An infinite iterator of `PartialOp`s, generated by calling a closure.
+///
+/// This allows computing ops on the fly, e.g. `PartialRead::new(r, PartialOpFn::new(|| ...))`.
+pub struct PartialOpFn<F> {
+    f: F,
+}
+
+impl<F: FnMut() -> PartialOp> PartialOpFn<F> {
+    /// Create a new `PartialOpFn`, yielding the results of calling `f`.
+    pub fn new(f: F) -> PartialOpFn<F> {
+        PartialOpFn { f }
+    }
+}
+
+impl<F: FnMut() -> PartialOp> Iterator for PartialOpFn<F> {
+    type Item = PartialOp;
+
+    fn next(&mut self) -> Option<PartialOp> {
+        Some((self.f)())
+    }
+}
+
+/// Wrap an iterator of `PartialOp`s, inserting a `PartialOp::Pending` after every `every` ops.
+pub fn interleave_pending<I>(every: usize, ops: I) -> InterleavePending<I>
+    where I: Iterator<Item = PartialOp>
+{
+    InterleavePending::new(every, ops)
+}
+
+/// An iterator adapter that inserts a `PartialOp::Pending` after every `every` ops of the inner
+/// iterator. An `every` of zero inserts no `Pending`s. Once the inner iterator returns `None`, so
+/// does this one, forever.
+///
+/// Obtained via `interleave_pending`.
+#[derive(Debug, Clone)]
+pub struct InterleavePending<I> {
+    inner: I,
+    every: usize,
+    count: usize,
+    done: bool,
+}
+
+impl<I> InterleavePending<I>
+    where I: Iterator<Item = PartialOp>
+{
+    /// Create a new `InterleavePending`, wrapping the given iterator.
+    pub fn new(every: usize, inner: I) -> InterleavePending<I> {
+        InterleavePending {
+            inner,
+            every,
+            count: 0,
+            done: false,
+        }
+    }
+}
+
+impl<I> Iterator for InterleavePending<I>
+    where I: Iterator<Item = PartialOp>
+{
+    type Item = PartialOp;
+
+    fn next(&mut self) -> Option<PartialOp> {
+        if self.done {
+            return None;
+        }
+
+        if self.every != 0 && self.count == self.every {
+            self.count = 0;
+            return Some(PartialOp::Pending);
+        }
+
+        let op = self.inner.next();
+        match op {
+            Some(_) => self.count += 1,
+            None => self.done = true,
+        }
+        op
+    }
+}
+
+/// Wrap an iterator of `PartialOp`s, recursively expanding all `PartialOp::Chain`s into the ops
+/// they contain.
+pub fn flatten<I>(ops: I) -> FlattenOps<I>
+    where I: Iterator<Item = PartialOp>
+{
+    FlattenOps {
+        inner: ops,
+        chains: Vec::new(),
+    }
+}
+
+/// An iterator adapter that expands all `PartialOp::Chain`s of the inner iterator, so that it
+/// never yields a `Chain`.
+///
+/// Obtained via `flatten`.
+#[derive(Debug, Clone)]
+pub struct FlattenOps<I> {
+    inner: I,
+    // The remaining ops of the chains that are currently being expanded, innermost last.
+    chains: Vec<vec::IntoIter<PartialOp>>,
+}
+
+impl<I> Iterator for FlattenOps<I>
+    where I: Iterator<Item = PartialOp>
+{
+    type Item = PartialOp;
+
+    fn next(&mut self) -> Option<PartialOp> {
+        loop {
+            let op = match self.chains.last_mut().map(Iterator::next) {
+                Some(Some(op)) => op,
+                Some(None) => {
+                    self.chains.pop();
+                    continue;
+                }
+                None => self.inner.next()?,
+            };
+
+            match op {
+                PartialOp::Chain(ops) => self.chains.push(ops.into_iter()),
+                op => return Some(op),
+            }
+        }
+    }
+}
+
+/// A reader that can tell whether a read of zero bytes signaled EOF, or whether more data may
+/// become available later.
+pub trait IsEof {
+    /// Returns whether the most recent read that returned zero bytes signaled EOF.
+    fn is_eof(&self) -> bool;
+}
+
+impl<'a> IsEof for &'a [u8] {
+    fn is_eof(&self) -> bool {
+        self.is_empty()
+    }
 }
 
 /// Wraps a reader and modifies its read operations according to the given iterator of `PartialOp`s.
+///
+/// If the wrapped value is also a writer, its write operations are modified by the same iterator,
+/// in the order in which reads and writes are performed.
 #[derive(Debug)]
 pub struct PartialRead<R, Ops> {
     reader: R,
-    ops: Ops,
+    ops: FlattenOps<Ops>,
+    stats: PartialReadStats,
+    strict_mode: bool,
+    eof_returned: bool,
+    auto_pending: Option<fn(&R) -> bool>,
+    split_remainder: VecDeque<u8>,
+    skip_remaining: usize,
 }
 
 impl<R, Ops> PartialRead<R, Ops> {
     /// Create a new `PartialRead`, wrapping the given `R` and modifying its io operations via the
     /// given `Ops`.
     pub fn new(reader: R, ops: Ops) -> PartialRead<R, Ops> {
-        PartialRead { reader, ops }
+        PartialRead {
+            reader,
+            ops: FlattenOps {
+                inner: ops,
+                chains: Vec::new(),
+            },
+            stats: PartialReadStats::default(),
+            strict_mode: false,
+            eof_returned: false,
+            auto_pending: None,
+            split_remainder: VecDeque::new(),
+            skip_remaining: 0,
+        }
+    }
+
+    /// Enable strict mode: once a read has signaled EOF by returning `Ok(Ready(0))`, any further
+    /// call to `poll_read` panics with "polled after EOF".
+    ///
+    /// Reads into an empty buffer and reads with a `PartialOp` that limits them to zero bytes
+    /// (`Limited(0)`) do not count as EOF.
+    pub fn strict(mut self) -> PartialRead<R, Ops> {
+        self.strict_mode = true;
+        self
+    }
+
+    /// Returns statistics about the read operations performed so far.
+    pub fn stats(&self) -> &PartialReadStats {
+        &self.stats
     }
 
     /// Gets a reference to the underlying `R`.
@@ -42,49 +376,311 @@ impl<R, Ops> PartialRead<R, Ops> {
         &mut self.reader
     }
 
-    /// Consumes this `PartialRead`, returning the underlying reader.
+    /// Consumes this `PartialRead`, returning the underlying reader. The remainder of a split
+    /// read that has not been delivered yet is discarded.
     pub fn into_inner(self) -> R {
         self.reader
     }
+
+    /// Panics if not all ops have been consumed, listing the remaining ones.
+    ///
+    /// Useful in test teardown, to assert that the code under test performed exactly as many
+    /// operations as expected.
+    pub fn assert_no_ops_remaining(&self)
+        where Ops: ExactSizeIterator<Item = PartialOp> + Clone
+    {
+        let remaining = self.ops.clone().collect::<Vec<_>>();
+        if !remaining.is_empty() {
+            panic!("PartialRead still has unconsumed ops: {:?}", remaining);
+        }
+    }
+
+    /// Create a new `PartialReadHooked`, which behaves like a `PartialRead` but additionally
+    /// calls `after_read` with the bytes returned by each successful, nonzero read.
+    pub fn with_after_read<F>(reader: R, ops: Ops, after_read: F) -> PartialReadHooked<R, Ops, F>
+        where F: FnMut(&[u8])
+    {
+        PartialReadHooked {
+            inner: PartialRead::new(reader, ops),
+            after_read,
+        }
+    }
 }
 
+impl<R, Ops> PartialRead<R, Ops>
+    where R: IsEof
+{
+    /// Enable auto-pending mode: whenever the wrapped reader returns `Ok(Ready(0))` without being
+    /// at EOF (according to its `IsEof` impl), return `Pending` instead.
+    ///
+    /// This prevents empty reads of the wrapped reader from being mistaken for EOF by the code
+    /// under test. Reads that would not count as EOF in strict mode are not affected.
+    pub fn auto_pending(mut self) -> PartialRead<R, Ops> {
+        self.auto_pending = Some(R::is_eof);
+        self
+    }
+}
+
+impl<R: AsyncRead, Ops> PartialRead<R, Ops> {
+    // Discard up to `skip_remaining` bytes of the wrapped reader, using `buf` as scratch space.
+    fn poll_skip(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if self.skip_remaining == 0 {
+            wk.wake();
+            return Pending;
+        }
+
+        let len = min(self.skip_remaining, buf.len());
+        match self.reader.poll_read(wk, &mut buf[..len]) {
+            Poll::Ready(Ok(0)) => Poll::Ready(Ok(0)),
+            Poll::Ready(Ok(read)) => {
+                self.skip_remaining -= read;
+                wk.wake();
+                Pending
+            }
+            other => other,
+        }
+    }
+}
+
+impl<R, Ops> PartialRead<R, Ops> {
+    // Whether auto-pending mode is enabled and the wrapped reader is not at EOF.
+    fn inner_not_eof(&self) -> bool {
+        match self.auto_pending {
+            Some(is_eof) => !is_eof(&self.reader),
+            None => false,
+        }
+    }
+}
+
+impl<R> PartialRead<R, vec::IntoIter<PartialOp>> {
+    /// Create a new `PartialRead` performing the given ops, resulting in a nameable
+    /// `SimplePartialRead`.
+    pub fn from_vec(reader: R, ops: Vec<PartialOp>) -> SimplePartialRead<R> {
+        PartialRead::new(reader, ops.into_iter())
+    }
+}
+
+impl<R> PartialRead<R, DelayOps> {
+    /// Create a new `PartialRead` that returns `Pending` `n_pending` times before performing all
+    /// further reads as normal.
+    pub fn with_delay(reader: R, n_pending: usize) -> PartialRead<R, DelayOps> {
+        PartialRead::new(reader, delay_ops(n_pending))
+    }
+}
+
+impl<R, W, Ops> PartialRead<Duplex<R, W>, Ops> {
+    /// Splits a `PartialRead` around a `Duplex` into a `PartialReadOnly` that applies the ops to
+    /// the reader, and the raw underlying writer.
+    ///
+    /// A `PartialRead` applies its ops to writes as well. Splitting it yields the raw writer, for
+    /// writing without any ops, and a reader that only consumes ops for reads.
+    ///
+    /// Auto-pending mode is not carried over to the `PartialReadOnly`.
+    pub fn split_rw(self) -> (PartialReadOnly<R, Ops>, W) {
+        let (r, w) = self.reader.into_inner();
+        (PartialReadOnly(PartialRead {
+             reader: r,
+             ops: self.ops,
+             stats: self.stats,
+             strict_mode: self.strict_mode,
+             eof_returned: self.eof_returned,
+             auto_pending: None,
+             split_remainder: self.split_remainder,
+             skip_remaining: self.skip_remaining,
+         }),
+         w)
+    }
+}
+
+impl<R, Ops> Unpin for PartialRead<R, Ops> {}
+
 impl<R, Ops> AsyncRead for PartialRead<R, Ops>
     where R: AsyncRead,
           Ops: Iterator<Item = PartialOp>
 {
     fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
-        match self.ops.next() {
-            None |
-            Some(PartialOp::Unlimited) => self.reader.poll_read(wk, buf),
-            Some(PartialOp::Pending) => {
+        if self.strict_mode && self.eof_returned {
+            panic!("polled after EOF");
+        }
+
+        if !self.split_remainder.is_empty() {
+            let len = min(self.split_remainder.len(), buf.len());
+            for (dst, src) in buf.iter_mut().zip(self.split_remainder.drain(..len)) {
+                *dst = src;
+            }
+            let res = Poll::Ready(Ok(len));
+            // Delivering the remainder is part of the split read.
+            self.stats.record(&Some(PartialOp::SplitAt(len)), &res);
+            return res;
+        }
+
+        let (op, res) = if self.skip_remaining > 0 {
+            // Continue an unfinished skip, without consuming an op.
+            (Some(PartialOp::Skip(self.skip_remaining)), self.poll_skip(wk, buf))
+        } else {
+            let op = self.ops.next();
+            let res = match op {
+                None |
+                Some(PartialOp::Unlimited) => self.reader.poll_read(wk, buf),
+                Some(PartialOp::Pending) => {
+                    wk.wake();
+                    Pending
+                }
+                Some(PartialOp::Limited(n)) => {
+                    let len = min(n, buf.len());
+                    self.reader.poll_read(wk, &mut buf[..len])
+                }
+                Some(PartialOp::Skip(n)) => {
+                    self.skip_remaining = n;
+                    self.poll_skip(wk, buf)
+                }
+                Some(PartialOp::SplitAt(n)) => {
+                    match self.reader.poll_read(wk, buf) {
+                        Poll::Ready(Ok(read)) if read > n => {
+                            self.split_remainder.extend(&buf[n..read]);
+                            if n == 0 {
+                                wk.wake();
+                                Pending
+                            } else {
+                                Poll::Ready(Ok(n))
+                            }
+                        }
+                        other => other,
+                    }
+                }
+                Some(PartialOp::Chain(_)) => unreachable!("chains are expanded by `flatten`"),
+            };
+            (op, res)
+        };
+
+        // Whether a result of `Ok(Ready(0))` signals EOF.
+        let eof = match op {
+            Some(PartialOp::Limited(0)) => false,
+            _ => !buf.is_empty(),
+        };
+
+        let res = match res {
+            Poll::Ready(Ok(0)) if eof && self.inner_not_eof() => {
                 wk.wake();
                 Pending
             }
-            Some(PartialOp::Limited(n)) => {
-                let len = min(n, buf.len());
-                self.reader.poll_read(wk, &mut buf[..len])
-            }
+            res => res,
+        };
+        self.stats.record(&op, &res);
+
+        if let Poll::Ready(Ok(0)) = res {
+            self.eof_returned |= eof;
         }
+
+        res
     }
 }
 
+/// Writes to a `PartialRead` are modified by the same ops iterator as reads, so every read, write,
+/// flush or close consumes the next op. Use `PartialRead::split_rw` to write to a wrapped `Duplex`
+/// without any ops.
 impl<W, Ops> AsyncWrite for PartialRead<W, Ops>
-    where W: AsyncWrite
+    where W: AsyncWrite,
+          Ops: Iterator<Item = PartialOp>
 {
     fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
-        self.reader.poll_write(wk, buf)
+        poll_write_op(&mut self.reader, self.ops.next(), wk, buf)
     }
 
     fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
-        self.reader.poll_flush(wk)
+        poll_flush_op(&mut self.reader, self.ops.next(), wk)
     }
 
     fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
-        self.reader.poll_close(wk)
+        poll_close_op(&mut self.reader, self.ops.next(), wk)
+    }
+}
+
+/// A `PartialRead` that only implements `AsyncRead`, even if the underlying `R` is also a writer.
+///
+/// Obtained via `PartialRead::split_rw`.
+#[derive(Debug)]
+pub struct PartialReadOnly<R, Ops>(PartialRead<R, Ops>);
+
+impl<R, Ops> PartialReadOnly<R, Ops> {
+    /// Gets a reference to the underlying `R`.
+    pub fn get_ref(&self) -> &R {
+        self.0.get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying `R`.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.0.get_mut()
+    }
+
+    /// Returns statistics about the read operations performed so far.
+    pub fn stats(&self) -> &PartialReadStats {
+        self.0.stats()
+    }
+
+    /// Consumes this `PartialReadOnly`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.0.into_inner()
+    }
+}
+
+impl<R, Ops> Unpin for PartialReadOnly<R, Ops> {}
+
+impl<R, Ops> AsyncRead for PartialReadOnly<R, Ops>
+    where R: AsyncRead,
+          Ops: Iterator<Item = PartialOp>
+{
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        self.0.poll_read(wk, buf)
+    }
+}
+
+/// Wraps a reader like a `PartialRead`, and calls a function with the bytes returned by each read.
+///
+/// The function is only called for reads that return `Ok(Ready(n))` with `n > 0`, e.g. for
+/// computing checksums or logging the data without modifying the code under test.
+///
+/// Obtained via `PartialRead::with_after_read`.
+pub struct PartialReadHooked<R, Ops, F> {
+    inner: PartialRead<R, Ops>,
+    after_read: F,
+}
+
+impl<R, Ops, F> PartialReadHooked<R, Ops, F> {
+    /// Gets a reference to the underlying `PartialRead`.
+    pub fn get_ref(&self) -> &PartialRead<R, Ops> {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying `PartialRead`.
+    pub fn get_mut(&mut self) -> &mut PartialRead<R, Ops> {
+        &mut self.inner
+    }
+
+    /// Consumes this `PartialReadHooked`, returning the underlying `PartialRead`.
+    pub fn into_inner(self) -> PartialRead<R, Ops> {
+        self.inner
     }
+}
+
+impl<R, Ops, F> Unpin for PartialReadHooked<R, Ops, F> {}
 
-    fn poll_vectored_write(&mut self, wk: &Waker, vec: &[&IoVec]) -> Poll<Result<usize, Error>> {
-        self.reader.poll_vectored_write(wk, vec)
+impl<R, Ops, F> AsyncRead for PartialReadHooked<R, Ops, F>
+    where R: AsyncRead,
+          Ops: Iterator<Item = PartialOp>,
+          F: FnMut(&[u8])
+{
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let res = self.inner.poll_read(wk, buf);
+        if let Poll::Ready(Ok(read)) = res {
+            if read > 0 {
+                (self.after_read)(&buf[..read]);
+            }
+        }
+        res
     }
 }
 
@@ -92,14 +688,44 @@ impl<W, Ops> AsyncWrite for PartialRead<W, Ops>
 #[derive(Debug)]
 pub struct PartialWrite<W, Ops> {
     writer: W,
-    ops: Ops,
+    ops: FlattenOps<Ops>,
+    strict_mode: bool,
+    closed: bool,
 }
 
 impl<W, Ops> PartialWrite<W, Ops> {
     /// Create a new `PartialWrite`, wrapping the given `W` and modifying its io operations via the
     /// given `Ops`.
     pub fn new(writer: W, ops: Ops) -> PartialWrite<W, Ops> {
-        PartialWrite { writer, ops }
+        PartialWrite {
+            writer,
+            ops: FlattenOps {
+                inner: ops,
+                chains: Vec::new(),
+            },
+            strict_mode: false,
+            closed: false,
+        }
+    }
+
+    /// Enable strict mode: once `poll_close` has returned `Ok(Ready(()))`, any further call to
+    /// `poll_write`, `poll_flush` or `poll_close` panics with "polled after close".
+    ///
+    /// Without strict mode, such calls return an error of kind `BrokenPipe` instead.
+    pub fn strict(mut self) -> PartialWrite<W, Ops> {
+        self.strict_mode = true;
+        self
+    }
+
+    fn check_closed(&self) -> Result<(), Error> {
+        if self.closed {
+            if self.strict_mode {
+                panic!("polled after close");
+            }
+            Err(Error::new(ErrorKind::BrokenPipe, "PartialWrite polled after close"))
+        } else {
+            Ok(())
+        }
     }
 
     /// Gets a reference to the underlying `W`.
@@ -116,45 +742,99 @@ impl<W, Ops> PartialWrite<W, Ops> {
     pub fn into_inner(self) -> W {
         self.writer
     }
+
+    /// Panics if not all ops have been consumed, listing the remaining ones.
+    ///
+    /// Useful in test teardown, to assert that the code under test performed exactly as many
+    /// operations as expected.
+    pub fn assert_no_ops_remaining(&self)
+        where Ops: ExactSizeIterator<Item = PartialOp> + Clone
+    {
+        let remaining = self.ops.clone().collect::<Vec<_>>();
+        if !remaining.is_empty() {
+            panic!("PartialWrite still has unconsumed ops: {:?}", remaining);
+        }
+    }
+
+    /// Create a new `PartialWriteEcho`, which behaves like a `PartialWrite` but additionally
+    /// allows reading back all bytes that have been written.
+    pub fn with_echo(writer: W, ops: Ops) -> PartialWriteEcho<W, Ops> {
+        PartialWriteEcho {
+            inner: PartialWrite::new(writer, ops),
+            echo: VecDeque::new(),
+            read_waker: None,
+        }
+    }
+
+    /// Create a new `CollectingPartialWrite`, which behaves like a `PartialWrite` but additionally
+    /// stores a copy of all bytes that have been written.
+    pub fn collecting(writer: W, ops: Ops) -> CollectingPartialWrite<W, Ops> {
+        CollectingPartialWrite {
+            inner: PartialWrite::new(writer, ops),
+            written: Vec::new(),
+        }
+    }
+
+    /// Create a new `AutoFlushPartialWrite`, which behaves like a `PartialWrite` but additionally
+    /// flushes the writer after every `flush_every` successful writes. A `flush_every` of zero
+    /// disables the automatic flushes.
+    pub fn auto_flush(writer: W, ops: Ops, flush_every: usize) -> AutoFlushPartialWrite<W, Ops> {
+        AutoFlushPartialWrite {
+            inner: PartialWrite::new(writer, ops),
+            flush_every,
+            writes_since_flush: 0,
+        }
+    }
 }
 
+impl<W> PartialWrite<W, vec::IntoIter<PartialOp>> {
+    /// Create a new `PartialWrite` performing the given ops, resulting in a nameable
+    /// `SimplePartialWrite`.
+    pub fn from_vec(writer: W, ops: Vec<PartialOp>) -> SimplePartialWrite<W> {
+        PartialWrite::new(writer, ops.into_iter())
+    }
+}
+
+impl<W> PartialWrite<W, DelayOps> {
+    /// Create a new `PartialWrite` that returns `Pending` `n_pending` times before performing all
+    /// further operations as normal.
+    pub fn with_delay(writer: W, n_pending: usize) -> PartialWrite<W, DelayOps> {
+        PartialWrite::new(writer, delay_ops(n_pending))
+    }
+}
+
+impl<W, Ops> Unpin for PartialWrite<W, Ops> {}
+
 impl<W, Ops> AsyncWrite for PartialWrite<W, Ops>
     where W: AsyncWrite,
           Ops: Iterator<Item = PartialOp>
 {
     fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
-        match self.ops.next() {
-            None |
-            Some(PartialOp::Unlimited) => self.writer.poll_write(wk, buf),
-            Some(PartialOp::Pending) => {
-                wk.wake();
-                Pending
-            }
-            Some(PartialOp::Limited(n)) => {
-                let len = min(n, buf.len());
-                self.writer.poll_write(wk, &buf[..len])
-            }
+        if let Err(e) = self.check_closed() {
+            return Poll::Ready(Err(e));
         }
+
+        poll_write_op(&mut self.writer, self.ops.next(), wk, buf)
     }
 
     fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
-        match self.ops.next() {
-            Some(PartialOp::Pending) => {
-                wk.wake();
-                Pending
-            }
-            _ => self.writer.poll_flush(wk),
+        if let Err(e) = self.check_closed() {
+            return Poll::Ready(Err(e));
         }
+
+        poll_flush_op(&mut self.writer, self.ops.next(), wk)
     }
 
     fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
-        match self.ops.next() {
-            Some(PartialOp::Pending) => {
-                wk.wake();
-                Pending
-            }
-            _ => self.writer.poll_close(wk),
+        if let Err(e) = self.check_closed() {
+            return Poll::Ready(Err(e));
         }
+
+        let res = poll_close_op(&mut self.writer, self.ops.next(), wk);
+        if let Poll::Ready(Ok(())) = res {
+            self.closed = true;
+        }
+        res
     }
 }
 
@@ -166,6 +846,287 @@ impl<W, Ops> AsyncRead for PartialWrite<W, Ops>
     }
 }
 
+/// Wraps a writer and modifies its write operations like a `PartialWrite`, while echoing all
+/// bytes that were successfully written back as reads, like a loopback connection.
+///
+/// Reading when no written bytes are buffered returns `Pending`, the task is woken by the next
+/// successful write.
+#[derive(Debug)]
+pub struct PartialWriteEcho<W, Ops> {
+    inner: PartialWrite<W, Ops>,
+    echo: VecDeque<u8>,
+    read_waker: Option<Waker>,
+}
+
+impl<W, Ops> PartialWriteEcho<W, Ops> {
+    /// Gets a reference to the underlying `W`.
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying `W`.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    /// Consumes this `PartialWriteEcho`, returning the underlying writer. Any bytes that have been
+    /// written but not yet read back are discarded.
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner()
+    }
+}
+
+impl<W, Ops> Unpin for PartialWriteEcho<W, Ops> {}
+
+impl<W, Ops> AsyncWrite for PartialWriteEcho<W, Ops>
+    where W: AsyncWrite,
+          Ops: Iterator<Item = PartialOp>
+{
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let res = self.inner.poll_write(wk, buf);
+        if let Poll::Ready(Ok(written)) = res {
+            self.echo.extend(&buf[..written]);
+            if written > 0 {
+                if let Some(read_waker) = self.read_waker.take() {
+                    read_waker.wake();
+                }
+            }
+        }
+        res
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_flush(wk)
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_close(wk)
+    }
+}
+
+impl<W, Ops> AsyncRead for PartialWriteEcho<W, Ops> {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        if self.echo.is_empty() && !buf.is_empty() {
+            self.read_waker = Some(wk.clone());
+            return Pending;
+        }
+
+        let len = min(self.echo.len(), buf.len());
+        for (dst, src) in buf.iter_mut().zip(self.echo.drain(..len)) {
+            *dst = src;
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+/// Wraps a writer and modifies its write operations like a `PartialWrite`, while also keeping a
+/// copy of all bytes that were successfully written to the inner writer.
+///
+/// Obtained via `PartialWrite::collecting`.
+#[derive(Debug)]
+pub struct CollectingPartialWrite<W, Ops> {
+    inner: PartialWrite<W, Ops>,
+    written: Vec<u8>,
+}
+
+impl<W, Ops> CollectingPartialWrite<W, Ops> {
+    /// Returns all bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Gets a reference to the underlying `W`.
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying `W`.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    /// Consumes this `CollectingPartialWrite`, returning the underlying writer and all bytes
+    /// written so far.
+    pub fn into_inner(self) -> (W, Vec<u8>) {
+        (self.inner.into_inner(), self.written)
+    }
+}
+
+impl<W, Ops> Unpin for CollectingPartialWrite<W, Ops> {}
+
+impl<W, Ops> AsyncWrite for CollectingPartialWrite<W, Ops>
+    where W: AsyncWrite,
+          Ops: Iterator<Item = PartialOp>
+{
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let res = self.inner.poll_write(wk, buf);
+        if let Poll::Ready(Ok(written)) = res {
+            self.written.extend_from_slice(&buf[..written]);
+        }
+        res
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_flush(wk)
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_close(wk)
+    }
+}
+
+impl<W, Ops> AsyncRead for CollectingPartialWrite<W, Ops>
+    where W: AsyncRead
+{
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        self.inner.poll_read(wk, buf)
+    }
+}
+
+/// Wraps a writer and modifies its write operations like a `PartialWrite`, while also inserting a
+/// flush after every `flush_every` successful writes.
+///
+/// The flush is performed by the next call to `poll_write`, which drives `poll_flush` to
+/// completion before writing. The flush goes through the `PartialWrite`, so it consumes an op and
+/// may return `Pending` several times. Explicit flushes reset the count of writes.
+///
+/// Obtained via `PartialWrite::auto_flush`.
+#[derive(Debug)]
+pub struct AutoFlushPartialWrite<W, Ops> {
+    inner: PartialWrite<W, Ops>,
+    flush_every: usize,
+    writes_since_flush: usize,
+}
+
+impl<W, Ops> AutoFlushPartialWrite<W, Ops> {
+    /// Gets a reference to the underlying `W`.
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying `W`.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    /// Consumes this `AutoFlushPartialWrite`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner()
+    }
+}
+
+impl<W, Ops> Unpin for AutoFlushPartialWrite<W, Ops> {}
+
+impl<W, Ops> AsyncWrite for AutoFlushPartialWrite<W, Ops>
+    where W: AsyncWrite,
+          Ops: Iterator<Item = PartialOp>
+{
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        if self.flush_every > 0 && self.writes_since_flush >= self.flush_every {
+            match self.poll_flush(wk) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Pending => return Pending,
+            }
+        }
+
+        let res = self.inner.poll_write(wk, buf);
+        if let Poll::Ready(Ok(_)) = res {
+            self.writes_since_flush += 1;
+        }
+        res
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        let res = self.inner.poll_flush(wk);
+        if let Poll::Ready(Ok(())) = res {
+            self.writes_since_flush = 0;
+        }
+        res
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        self.inner.poll_close(wk)
+    }
+}
+
+impl<W, Ops> AsyncRead for AutoFlushPartialWrite<W, Ops>
+    where W: AsyncRead
+{
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        self.inner.poll_read(wk, buf)
+    }
+}
+
+/// Drives a reader to completion by polling it repeatedly, and returns all bytes read.
+///
+/// `Pending` and `Interrupted` results are retried immediately, without waiting for a wakeup. This
+/// only terminates for readers that eventually make progress regardless, such as a `PartialRead`
+/// over in-memory data.
+pub fn simulate_read<R: AsyncRead>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let wk = noop_waker();
+    let mut out = Vec::new();
+    let mut buf = [0; 1024];
+
+    loop {
+        match reader.poll_read(&wk, &mut buf) {
+            Poll::Ready(Ok(0)) => return Ok(out),
+            Poll::Ready(Ok(read)) => out.extend_from_slice(&buf[..read]),
+            Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+            Poll::Ready(Err(e)) => return Err(e),
+            Pending => {}
+        }
+    }
+}
+
+/// Writes all of `data` to a writer and then flushes it, by polling it repeatedly.
+///
+/// `Pending` and `Interrupted` results are retried immediately, without waiting for a wakeup, just
+/// like in `simulate_read`. Fails with an error of kind `WriteZero` if the writer stops accepting
+/// data.
+pub fn simulate_write<W: AsyncWrite>(writer: &mut W, data: &[u8]) -> Result<(), Error> {
+    let wk = noop_waker();
+    let mut written = 0;
+
+    while written < data.len() {
+        match writer.poll_write(&wk, &data[written..]) {
+            Poll::Ready(Ok(0)) => {
+                return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer"))
+            }
+            Poll::Ready(Ok(n)) => written += n,
+            Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+            Poll::Ready(Err(e)) => return Err(e),
+            Pending => {}
+        }
+    }
+
+    loop {
+        match writer.poll_flush(&wk) {
+            Poll::Ready(Ok(())) => return Ok(()),
+            Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+            Poll::Ready(Err(e)) => return Err(e),
+            Pending => {}
+        }
+    }
+}
+
+/// Wraps `reader` in a `PartialRead` performing the given ops, and reads it to completion via
+/// `simulate_read`.
+pub fn partial_read_all<R: AsyncRead>(reader: R, ops: Vec<PartialOp>) -> Result<Vec<u8>, Error> {
+    simulate_read(&mut PartialRead::from_vec(reader, ops))
+}
+
+/// Wraps `writer` in a `PartialWrite` performing the given ops, and writes all of `data` to it via
+/// `simulate_write`.
+///
+/// Pass a `&mut` reference as the writer to inspect it afterwards.
+pub fn partial_write_all<W: AsyncWrite>(writer: W,
+                                        ops: Vec<PartialOp>,
+                                        data: &[u8])
+                                        -> Result<(), Error> {
+    simulate_write(&mut PartialWrite::from_vec(writer, ops), data)
+}
+
 #[cfg(feature = "quickcheck")]
 mod qs {
     use super::*;
@@ -179,13 +1140,11 @@ mod qs {
                 PartialOp::Pending
             } else if rnd < 0.4 {
                 PartialOp::Unlimited
+            } else if g.size() <= 1 {
+                PartialOp::Limited(1)
             } else {
-                if g.size() <= 1 {
-                    PartialOp::Limited(1)
-                } else {
-                    let max = g.size();
-                    PartialOp::Limited(g.gen_range(1, max))
-                }
+                let max = g.size();
+                PartialOp::Limited(g.gen_range(1, max))
             }
         }
 
@@ -194,11 +1153,97 @@ mod qs {
                 PartialOp::Limited(n) => {
                     Box::new(n.shrink().filter(|k| k != &0).map(PartialOp::Limited))
                 }
+                PartialOp::Skip(n) => Box::new(n.shrink().map(PartialOp::Skip)),
+                PartialOp::SplitAt(n) => {
+                    Box::new(n.shrink().filter(|k| k != &0).map(PartialOp::SplitAt))
+                }
+                PartialOp::Chain(ref ops) => Box::new(ops.shrink().map(PartialOp::Chain)),
                 _ => empty_shrinker(),
             }
         }
     }
+
+    /// A sequence of `PartialOp`s with a shrinking strategy tailored to them.
+    ///
+    /// Shrinking first tries removing `Pending` ops, front to back, since those are the most
+    /// likely to be irrelevant to a failure. It then tries shrinking individual `Limited` ops, and
+    /// finally falls back to the generic shrinking of `Vec`s.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct QuickCheckPartialOps(pub Vec<PartialOp>);
+
+    impl Arbitrary for QuickCheckPartialOps {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            QuickCheckPartialOps(Vec::arbitrary(g))
+        }
+
+        fn shrink(&self) -> Box<Iterator<Item = Self>> {
+            let ops = &self.0;
+            let mut candidates = Vec::new();
+
+            for (i, _) in ops.iter().enumerate().filter(|&(_, op)| *op == PartialOp::Pending) {
+                let mut shrunk = ops.clone();
+                shrunk.remove(i);
+                candidates.push(QuickCheckPartialOps(shrunk));
+            }
+
+            for (i, op) in ops.iter().enumerate() {
+                if let PartialOp::Limited(_) = *op {
+                    for smaller in op.shrink() {
+                        let mut shrunk = ops.clone();
+                        shrunk[i] = smaller;
+                        candidates.push(QuickCheckPartialOps(shrunk));
+                    }
+                }
+            }
+
+            Box::new(candidates.into_iter().chain(ops.shrink().map(QuickCheckPartialOps)))
+        }
+    }
 }
 
 #[cfg(feature = "quickcheck")]
 pub use self::qs::*;
+
+#[cfg(all(test, feature = "quickcheck"))]
+mod tests {
+    use super::*;
+
+    use VecWriter;
+
+    quickcheck! {
+        fn read_never_exceeds_source(ops: QuickCheckPartialOps, data: Vec<u8>) -> bool {
+            let wk = noop_waker();
+            let mut r = PartialRead::new(&data[..], ops.0.into_iter());
+            let mut buf = [0; 8];
+            let mut total = 0;
+
+            loop {
+                match r.poll_read(&wk, &mut buf) {
+                    Poll::Ready(Ok(0)) => break,
+                    Poll::Ready(Ok(read)) => total += read,
+                    Poll::Ready(Err(e)) => panic!("{}", e),
+                    Pending => {}
+                }
+            }
+
+            total <= data.len()
+        }
+
+        fn write_never_exceeds_source(ops: QuickCheckPartialOps, data: Vec<u8>) -> bool {
+            let wk = noop_waker();
+            let mut w = PartialWrite::new(VecWriter::new(), ops.0.into_iter());
+            let mut total = 0;
+
+            while total < data.len() {
+                match w.poll_write(&wk, &data[total..]) {
+                    Poll::Ready(Ok(0)) => break,
+                    Poll::Ready(Ok(written)) => total += written,
+                    Poll::Ready(Err(e)) => panic!("{}", e),
+                    Pending => {}
+                }
+            }
+
+            total <= data.len() && w.get_ref().get_ref().len() <= data.len()
+        }
+    }
+}
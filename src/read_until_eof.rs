@@ -0,0 +1,62 @@
+//! A future that reads all bytes from a reader, reporting its progress along the way.
+
+use std::io::{Error, ErrorKind};
+use std::mem;
+use std::pin::Pin;
+use std::task::{Poll, Waker};
+
+use futures_core::future::Future;
+use futures_io::AsyncRead;
+
+/// Create a future that reads from `reader` until EOF and resolves to all bytes that were read.
+///
+/// After each successful read, `progress` is called with the total number of bytes read so far.
+/// Errors of kind `Interrupted` are retried.
+pub fn read_until_eof_with_progress<R, F>(reader: &mut R,
+                                          progress: F)
+                                          -> ReadUntilEof<'_, R, F>
+    where R: AsyncRead,
+          F: FnMut(usize)
+{
+    ReadUntilEof {
+        reader,
+        progress,
+        buf: Vec::new(),
+    }
+}
+
+/// Future returned by `read_until_eof_with_progress`.
+#[derive(Debug)]
+pub struct ReadUntilEof<'a, R: 'a, F> {
+    reader: &'a mut R,
+    progress: F,
+    buf: Vec<u8>,
+}
+
+// Never projects a pin to any of its fields.
+impl<'a, R, F> Unpin for ReadUntilEof<'a, R, F> {}
+
+impl<'a, R, F> Future for ReadUntilEof<'a, R, F>
+    where R: AsyncRead,
+          F: FnMut(usize)
+{
+    type Output = Result<Vec<u8>, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, wk: &Waker) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut chunk = [0; 1024];
+
+        loop {
+            match this.reader.poll_read(wk, &mut chunk) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(mem::replace(&mut this.buf, Vec::new()))),
+                Poll::Ready(Ok(read)) => {
+                    this.buf.extend_from_slice(&chunk[..read]);
+                    (this.progress)(this.buf.len());
+                }
+                Poll::Ready(Err(ref e)) if e.kind() == ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use std::task::{Poll, Waker};
+use std::io::Error;
+
+use futures_io::AsyncWrite;
+
+/// Implements AsyncWrite by discarding all written bytes, the asynchronous counterpart to
+/// `std::io::sink()`.
+///
+/// Writes always succeed immediately, flushing and closing do nothing.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SinkWriter;
+
+impl AsyncWrite for SinkWriter {
+    fn poll_write(&mut self, _: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
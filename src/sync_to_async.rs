@@ -0,0 +1,107 @@
+//! Adapters for using synchronous `std::io` readers and writers where `futures_io` ones are
+//! expected.
+
+use std::io::{Error, Read, Write};
+use std::task::{Poll, Waker};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+/// Implements AsyncRead by delegating to a synchronous `std::io::Read`.
+///
+/// Reads never return `Pending`, they block until the inner reader returns. Errors of kind
+/// `Interrupted` are retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncToAsyncRead<R> {
+    inner: R,
+}
+
+impl<R: Read> SyncToAsyncRead<R> {
+    /// Create a new `SyncToAsyncRead`, wrapping the given reader.
+    pub fn new(inner: R) -> SyncToAsyncRead<R> {
+        SyncToAsyncRead { inner }
+    }
+
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(retry!(self.inner.read(buf)))
+    }
+}
+
+impl<R> SyncToAsyncRead<R> {
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes this `SyncToAsyncRead`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> AsyncRead for SyncToAsyncRead<R> {
+    fn poll_read(&mut self, _: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        Poll::Ready(self.read_sync(buf))
+    }
+}
+
+/// Implements AsyncWrite by delegating to a synchronous `std::io::Write`.
+///
+/// Operations never return `Pending`, they block until the inner writer returns. Errors of kind
+/// `Interrupted` are retried. Since synchronous writers have no notion of closing, `poll_close`
+/// flushes the inner writer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncToAsyncWrite<W> {
+    inner: W,
+}
+
+impl<W: Write> SyncToAsyncWrite<W> {
+    /// Create a new `SyncToAsyncWrite`, wrapping the given writer.
+    pub fn new(inner: W) -> SyncToAsyncWrite<W> {
+        SyncToAsyncWrite { inner }
+    }
+
+    fn write_sync(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Ok(retry!(self.inner.write(buf)))
+    }
+
+    fn flush_sync(&mut self) -> Result<(), Error> {
+        retry!(self.inner.flush());
+        Ok(())
+    }
+}
+
+impl<W> SyncToAsyncWrite<W> {
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consumes this `SyncToAsyncWrite`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> AsyncWrite for SyncToAsyncWrite<W> {
+    fn poll_write(&mut self, _: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        Poll::Ready(self.write_sync(buf))
+    }
+
+    fn poll_flush(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(self.flush_sync())
+    }
+
+    fn poll_close(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(self.flush_sync())
+    }
+}
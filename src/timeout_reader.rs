@@ -0,0 +1,119 @@
+//! A reader that times out according to a manually advanced clock, for testing timeout handling
+//! without real timers.
+
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+
+use futures_io::AsyncRead;
+
+#[derive(Debug, Default)]
+struct ClockState {
+    ticks: usize,
+    wakers: Vec<Waker>,
+}
+
+/// A clock that only advances when `tick` is called. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Arc<Mutex<ClockState>>);
+
+impl MockClock {
+    /// Create a new `MockClock`, starting at zero ticks.
+    pub fn new() -> MockClock {
+        MockClock::default()
+    }
+
+    /// Returns the number of ticks that have passed so far.
+    pub fn now(&self) -> usize {
+        self.0.lock().unwrap().ticks
+    }
+
+    /// Advance the clock by one tick, waking all tasks waiting on it.
+    pub fn tick(&self) {
+        let wakers = {
+            let mut state = self.0.lock().unwrap();
+            state.ticks += 1;
+            state.wakers.split_off(0)
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn register(&self, wk: &Waker) {
+        let mut state = self.0.lock().unwrap();
+        if !state.wakers.iter().any(|waker| waker.will_wake(wk)) {
+            state.wakers.push(wk.clone());
+        }
+    }
+}
+
+/// Create a new `TimeoutReader`, wrapping the given reader and timing out after `ticks` ticks
+/// of the given clock.
+pub fn with_timeout<R>(reader: R, clock: &MockClock, ticks: usize) -> TimeoutReader<R> {
+    TimeoutReader::new(reader, clock.clone(), ticks)
+}
+
+/// Wraps a reader and fails with an error of kind `TimedOut` if a read remains pending while a
+/// `MockClock` advances by `duration` ticks.
+///
+/// The timeout starts with the first poll of a read, and is reset whenever a read completes.
+#[derive(Debug)]
+pub struct TimeoutReader<R> {
+    inner: R,
+    clock: MockClock,
+    duration: usize,
+    started: Option<usize>,
+}
+
+impl<R> TimeoutReader<R> {
+    /// Create a new `TimeoutReader`, wrapping the given reader and timing out after `duration`
+    /// ticks of the given clock.
+    pub fn new(inner: R, clock: MockClock, duration: usize) -> TimeoutReader<R> {
+        TimeoutReader {
+            inner,
+            clock,
+            duration,
+            started: None,
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes this `TimeoutReader`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for TimeoutReader<R> {
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let now = self.clock.now();
+        let started = *self.started.get_or_insert(now);
+
+        if now - started >= self.duration {
+            self.started = None;
+            return Poll::Ready(Err(Error::new(ErrorKind::TimedOut, "TimeoutReader timed out")));
+        }
+
+        match self.inner.poll_read(wk, buf) {
+            Poll::Pending => {
+                self.clock.register(wk);
+                Poll::Pending
+            }
+            ready => {
+                self.started = None;
+                ready
+            }
+        }
+    }
+}
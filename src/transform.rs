@@ -0,0 +1,205 @@
+//! Wrappers that transform the bytes passing through a partial reader or writer, e.g. to simulate
+//! an encryption or compression layer.
+//!
+//! Like the partial wrappers, these never pin their fields and are always `Unpin`.
+
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::task::{Poll, Waker};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use partial::{PartialOp, PartialRead, PartialWrite};
+
+impl<R, Ops> PartialRead<R, Ops> {
+    /// Create a `TransformRead` that applies `f` to all bytes read from this `PartialRead`.
+    pub fn transform<F>(self, f: F) -> TransformRead<R, Ops, F>
+        where F: FnMut(&[u8]) -> Vec<u8>
+    {
+        TransformRead::new(self, f)
+    }
+}
+
+/// Wraps a `PartialRead` and transforms the bytes it reads before delivering them.
+///
+/// If a transformed chunk does not fit into the buffer passed to `poll_read`, the remainder is
+/// delivered by subsequent reads before the inner reader is polled again.
+pub struct TransformRead<R, Ops, F> {
+    inner: PartialRead<R, Ops>,
+    transform: F,
+    buffered: VecDeque<u8>,
+}
+
+impl<R, Ops, F> TransformRead<R, Ops, F>
+    where F: FnMut(&[u8]) -> Vec<u8>
+{
+    /// Create a new `TransformRead`, applying `transform` to all bytes read from `inner`.
+    pub fn new(inner: PartialRead<R, Ops>, transform: F) -> TransformRead<R, Ops, F> {
+        TransformRead {
+            inner,
+            transform,
+            buffered: VecDeque::new(),
+        }
+    }
+}
+
+impl<R, Ops, F> TransformRead<R, Ops, F> {
+    /// Gets a reference to the underlying `PartialRead`.
+    pub fn get_ref(&self) -> &PartialRead<R, Ops> {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying `PartialRead`.
+    pub fn get_mut(&mut self) -> &mut PartialRead<R, Ops> {
+        &mut self.inner
+    }
+
+    /// Consumes this `TransformRead`, returning the underlying `PartialRead`. Transformed bytes
+    /// that have not been read yet are discarded.
+    pub fn into_inner(self) -> PartialRead<R, Ops> {
+        self.inner
+    }
+}
+
+impl<R, Ops, F> Unpin for TransformRead<R, Ops, F> {}
+
+impl<R, Ops, F> AsyncRead for TransformRead<R, Ops, F>
+    where R: AsyncRead,
+          Ops: Iterator<Item = PartialOp>,
+          F: FnMut(&[u8]) -> Vec<u8>
+{
+    fn poll_read(&mut self, wk: &Waker, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        while self.buffered.is_empty() {
+            match self.inner.poll_read(wk, buf) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(0)),
+                Poll::Ready(Ok(read)) => self.buffered.extend((self.transform)(&buf[..read])),
+                other => return other,
+            }
+        }
+
+        let len = min(self.buffered.len(), buf.len());
+        for (dst, src) in buf.iter_mut().zip(self.buffered.drain(..len)) {
+            *dst = src;
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<W, Ops> PartialWrite<W, Ops> {
+    /// Create a `TransformWrite` that applies `f` to all bytes before writing them to this
+    /// `PartialWrite`.
+    pub fn transform<F>(self, f: F) -> TransformWrite<W, Ops, F>
+        where F: FnMut(&[u8]) -> Vec<u8>
+    {
+        TransformWrite::new(self, f)
+    }
+}
+
+/// Wraps a `PartialWrite` and transforms the bytes passed to `poll_write` before writing them.
+///
+/// A write is reported as complete as soon as its transformed bytes have been buffered, unless the
+/// first attempt to write them to the inner writer fails, in which case the error is returned and
+/// the bytes are discarded. Buffered bytes are written to the inner writer before any further
+/// write, and by `poll_flush` and `poll_close`.
+pub struct TransformWrite<W, Ops, F> {
+    inner: PartialWrite<W, Ops>,
+    transform: F,
+    buffered: Vec<u8>,
+}
+
+impl<W, Ops, F> TransformWrite<W, Ops, F>
+    where F: FnMut(&[u8]) -> Vec<u8>
+{
+    /// Create a new `TransformWrite`, applying `transform` to all bytes written to `inner`.
+    pub fn new(inner: PartialWrite<W, Ops>, transform: F) -> TransformWrite<W, Ops, F> {
+        TransformWrite {
+            inner,
+            transform,
+            buffered: Vec::new(),
+        }
+    }
+}
+
+impl<W, Ops, F> TransformWrite<W, Ops, F> {
+    /// Gets a reference to the underlying `PartialWrite`.
+    pub fn get_ref(&self) -> &PartialWrite<W, Ops> {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying `PartialWrite`.
+    pub fn get_mut(&mut self) -> &mut PartialWrite<W, Ops> {
+        &mut self.inner
+    }
+
+    /// Consumes this `TransformWrite`, returning the underlying `PartialWrite`. Transformed bytes
+    /// that have not been written yet are discarded.
+    pub fn into_inner(self) -> PartialWrite<W, Ops> {
+        self.inner
+    }
+}
+
+impl<W, Ops, F> TransformWrite<W, Ops, F>
+    where W: AsyncWrite,
+          Ops: Iterator<Item = PartialOp>
+{
+    fn poll_write_buffered(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        while !self.buffered.is_empty() {
+            match self.inner.poll_write(wk, &self.buffered) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(Error::new(ErrorKind::WriteZero,
+                                                      "failed to write transformed bytes")))
+                }
+                Poll::Ready(Ok(written)) => {
+                    self.buffered.drain(..written);
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W, Ops, F> Unpin for TransformWrite<W, Ops, F> {}
+
+impl<W, Ops, F> AsyncWrite for TransformWrite<W, Ops, F>
+    where W: AsyncWrite,
+          Ops: Iterator<Item = PartialOp>,
+          F: FnMut(&[u8]) -> Vec<u8>
+{
+    fn poll_write(&mut self, wk: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        match self.poll_write_buffered(wk) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        self.buffered = (self.transform)(buf);
+        // If the inner writer is not ready, the bytes still count as accepted and writing them
+        // out is retried on the next call. An error means they can not be written at all.
+        if let Poll::Ready(Err(e)) = self.poll_write_buffered(wk) {
+            self.buffered.clear();
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        match self.poll_write_buffered(wk) {
+            Poll::Ready(Ok(())) => self.inner.poll_flush(wk),
+            other => other,
+        }
+    }
+
+    fn poll_close(&mut self, wk: &Waker) -> Poll<Result<(), Error>> {
+        match self.poll_write_buffered(wk) {
+            Poll::Ready(Ok(())) => self.inner.poll_close(wk),
+            other => other,
+        }
+    }
+}
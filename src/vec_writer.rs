@@ -0,0 +1,53 @@
+use std::task::{Poll, Waker};
+use std::io::Error;
+
+use futures_io::AsyncWrite;
+
+/// Implements AsyncWrite by appending all written bytes to a `Vec<u8>`.
+///
+/// Writes always succeed immediately, flushing and closing do nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VecWriter(Vec<u8>);
+
+impl VecWriter {
+    /// Creates a new, empty `VecWriter`.
+    pub fn new() -> VecWriter {
+        VecWriter(Vec::new())
+    }
+
+    /// Gets a reference to the bytes written so far.
+    pub fn get_ref(&self) -> &Vec<u8> {
+        &self.0
+    }
+
+    /// Gets a mutable reference to the bytes written so far.
+    pub fn get_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+
+    /// Unwraps this `VecWriter`, returning the bytes written so far.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for VecWriter {
+    fn from(v: Vec<u8>) -> VecWriter {
+        VecWriter(v)
+    }
+}
+
+impl AsyncWrite for VecWriter {
+    fn poll_write(&mut self, _: &Waker, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        self.0.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(&mut self, _: &Waker) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,309 @@
+#![feature(futures_api)]
+
+#[macro_use]
+extern crate atm_io_utils;
+extern crate futures_core;
+extern crate futures_io;
+
+use std::marker::PhantomPinned;
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Poll, RawWaker, RawWakerVTable, Waker};
+
+use futures_core::future::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use atm_io_utils::{Duplex, VecWriter};
+use atm_io_utils::copy_exact::copy_exact;
+use atm_io_utils::future_reader::FutureReader;
+use atm_io_utils::io_pair::IoPair;
+use atm_io_utils::mock_duplex::{MockDuplex, ReadResponse};
+use atm_io_utils::op_encoding::{decode_ops, encode_ops, DecodeError, MAX_CHAIN_DEPTH};
+use atm_io_utils::partial::{flatten, interleave_pending, partial_read_all, simulate_read,
+                            simulate_write, PartialOp, PartialRead, PartialWrite};
+
+unsafe fn noop_clone(_: *const ()) -> RawWaker {
+    noop_raw_waker()
+}
+
+unsafe fn noop(_: *const ()) {}
+
+static NOOP_VTABLE: RawWakerVTable = RawWakerVTable {
+    clone: noop_clone,
+    wake: noop,
+    drop: noop,
+};
+
+fn noop_raw_waker() -> RawWaker {
+    RawWaker::new(ptr::null(), &NOOP_VTABLE)
+}
+
+fn block_on<F: Future + Unpin>(mut fut: F) -> F::Output {
+    let wk = unsafe { Waker::new_unchecked(noop_raw_waker()) };
+    loop {
+        if let Poll::Ready(out) = Pin::new(&mut fut).poll(&wk) {
+            return out;
+        }
+    }
+}
+
+#[test]
+fn copy_exact_between_partial_wrappers() {
+    let data: Vec<u8> = (0..200).collect();
+
+    let mut r = PartialRead::new(&data[..],
+                                 vec![PartialOp::Pending,
+                                      PartialOp::Limited(7),
+                                      PartialOp::Pending,
+                                      PartialOp::Limited(3),
+                                      PartialOp::Unlimited]
+                                         .into_iter());
+    let mut w = PartialWrite::new(VecWriter::new(),
+                                  vec![PartialOp::Limited(5),
+                                       PartialOp::Pending,
+                                       PartialOp::Limited(50)]
+                                          .into_iter());
+
+    let copied = block_on(copy_exact(&mut r, &mut w, 150)).unwrap();
+
+    assert_eq!(copied, 150);
+    assert_eq!(&w.get_ref().get_ref()[..], &data[..150]);
+    assert_eq!(r.stats().pending_polls, 2);
+    r.assert_no_ops_remaining();
+    w.assert_no_ops_remaining();
+}
+
+#[test]
+fn copy_exact_fails_on_early_eof() {
+    let data = [1, 2, 3];
+    let mut r = PartialRead::with_delay(&data[..], 2);
+    let mut w = VecWriter::new();
+
+    let err = block_on(copy_exact(&mut r, &mut w, 4)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn assert_partial_read_eq_drives_reader() {
+    let data = [1, 2, 3, 4, 5];
+    let mut r = PartialRead::from_vec(&data[..],
+                                      vec![PartialOp::Limited(2), PartialOp::Pending]);
+    assert_partial_read_eq!(r, data);
+}
+
+#[test]
+fn auto_pending_hides_empty_reads() {
+    let mock = MockDuplex::with_responses(vec![ReadResponse::Data(vec![1, 2]),
+                                               ReadResponse::Data(vec![]),
+                                               ReadResponse::Data(vec![3]),
+                                               ReadResponse::Eof]);
+    let mut r = PartialRead::new(mock, std::iter::repeat(PartialOp::Unlimited)).auto_pending();
+    assert_partial_read_eq!(r, [1, 2, 3]);
+    assert_eq!(r.stats().pending_polls, 1);
+}
+
+#[test]
+fn close_both_signals_eof_on_read_half() {
+    let data = [1, 2, 3];
+    let mut duplex = Duplex::new(&data[..], VecWriter::new());
+
+    block_on(duplex.close_both()).unwrap();
+    assert_eq!(simulate_read(&mut duplex).unwrap(), Vec::<u8>::new());
+
+    let mut duplex = duplex.map_reader(|r| r);
+    assert_eq!(simulate_read(&mut duplex).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn split_at_delivers_remainder_without_consuming_ops() {
+    let data = [1, 2, 3, 4, 5];
+    let mut r = PartialRead::from_vec(&data[..], vec![PartialOp::SplitAt(2), PartialOp::Pending]);
+    let wk = unsafe { Waker::new_unchecked(noop_raw_waker()) };
+    let mut buf = [0; 8];
+
+    {
+        let mut read = |buf: &mut [u8]| match r.poll_read(&wk, buf) {
+            Poll::Ready(Ok(read)) => read,
+            other => panic!("unexpected read result: {:?}", other),
+        };
+        assert_eq!(read(&mut buf), 2);
+        assert_eq!(&buf[..2], &[1, 2]);
+        assert_eq!(read(&mut buf[..2]), 2);
+        assert_eq!(&buf[..2], &[3, 4]);
+        assert_eq!(read(&mut buf), 1);
+        assert_eq!(buf[0], 5);
+    }
+    assert_eq!(r.stats().split_polls, 3);
+}
+
+#[test]
+fn chains_are_flattened_and_encoded() {
+    let ops = vec![PartialOp::Limited(1),
+                   PartialOp::Chain(vec![PartialOp::Pending,
+                                         PartialOp::Chain(vec![]),
+                                         PartialOp::Chain(vec![PartialOp::Limited(2)])]),
+                   PartialOp::Unlimited];
+
+    assert_eq!(flatten(ops.clone().into_iter()).collect::<Vec<_>>(),
+               vec![PartialOp::Limited(1),
+                    PartialOp::Pending,
+                    PartialOp::Limited(2),
+                    PartialOp::Unlimited]);
+    assert_eq!(decode_ops(&encode_ops(&ops)), Ok(ops.clone()));
+
+    let data = [1, 2, 3, 4, 5];
+    let mut r = PartialRead::from_vec(&data[..], ops);
+    assert_partial_read_eq!(r, data);
+    assert_eq!(r.stats().limited_polls, 2);
+}
+
+#[test]
+fn skip_delivers_data_after_the_skipped_bytes() {
+    let data = [1, 2, 3, 4];
+    let read = partial_read_all(&data[..],
+                                vec![PartialOp::Limited(1), PartialOp::Skip(1)]);
+    assert_eq!(read.unwrap(), vec![1, 3, 4]);
+    assert_eq!(partial_read_all(&data[..], vec![PartialOp::Skip(usize::max_value())]).unwrap(),
+               Vec::<u8>::new());
+
+    // The whole header is skipped, even when reading a single byte at a time.
+    let data = [9, 9, 9, 9, 1, 2];
+    let mut r = PartialRead::from_vec(&data[..], vec![PartialOp::Skip(4)]);
+    let wk = unsafe { Waker::new_unchecked(noop_raw_waker()) };
+    let mut read = Vec::new();
+    let mut buf = [0; 1];
+    loop {
+        match r.poll_read(&wk, &mut buf) {
+            Poll::Ready(Ok(0)) => break,
+            Poll::Ready(Ok(n)) => read.extend_from_slice(&buf[..n]),
+            Poll::Ready(Err(e)) => panic!("unexpected read error: {}", e),
+            Poll::Pending => {}
+        }
+    }
+    assert_eq!(read, vec![1, 2]);
+    assert_eq!(r.stats().skip_polls, 4);
+}
+
+#[test]
+fn io_pair_write_fails_after_other_end_is_dropped() {
+    let (mut a, b) = IoPair::new(4);
+    let wk = unsafe { Waker::new_unchecked(noop_raw_waker()) };
+
+    match a.poll_write(&wk, &[1, 2, 3, 4]) {
+        Poll::Ready(Ok(4)) => {}
+        other => panic!("unexpected write result: {:?}", other),
+    }
+    drop(b);
+    match a.poll_write(&wk, &[5]) {
+        Poll::Ready(Err(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        other => panic!("unexpected write result: {:?}", other),
+    }
+}
+
+#[test]
+fn hybrid_duplex_records_writes_next_to_a_real_reader() {
+    let data = [1, 2, 3];
+    let mut duplex = MockDuplex::with_reader(PartialRead::from_vec(&data[..],
+                                                                    vec![PartialOp::Limited(1)]));
+
+    assert_eq!(simulate_read(&mut duplex).unwrap(), vec![1, 2, 3]);
+    simulate_write(&mut duplex, &[4, 5]).unwrap();
+    assert_eq!(duplex.written(), &[4, 5]);
+}
+
+#[test]
+fn mock_duplex_checks_write_sequence() {
+    let mut duplex = MockDuplex::new();
+    duplex.expect_write_sequence(vec![vec![1, 2], vec![3]]);
+
+    simulate_write(&mut duplex, &[1, 2]).unwrap();
+    simulate_write(&mut duplex, &[3]).unwrap();
+    duplex.assert_sequence_complete();
+}
+
+#[test]
+#[should_panic(expected = "mismatch")]
+fn mock_duplex_panics_on_unexpected_write() {
+    let mut duplex = MockDuplex::new();
+    duplex.expect_write_sequence(vec![vec![1, 2]]);
+
+    simulate_write(&mut duplex, &[1]).unwrap();
+}
+
+struct NotUnpinData(PhantomPinned);
+
+impl Future for NotUnpinData {
+    type Output = std::io::Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, _: &Waker) -> Poll<Self::Output> {
+        Poll::Ready(Ok(vec![1, 2, 3]))
+    }
+}
+
+#[test]
+fn future_reader_accepts_futures_that_are_not_unpin() {
+    let mut r = FutureReader::new(NotUnpinData(PhantomPinned));
+    assert_eq!(simulate_read(&mut r).unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn interleave_pending_stops_after_inner_ops() {
+    let inner = vec![PartialOp::Limited(1), PartialOp::Limited(2)];
+    let mut ops = interleave_pending(2, inner.into_iter());
+
+    assert_eq!(ops.next(), Some(PartialOp::Limited(1)));
+    assert_eq!(ops.next(), Some(PartialOp::Limited(2)));
+    assert_eq!(ops.next(), Some(PartialOp::Pending));
+    for _ in 0..4 {
+        assert_eq!(ops.next(), None);
+    }
+}
+
+#[test]
+fn split_at_zero_does_not_lose_data() {
+    let data = [1, 2, 3, 4];
+    assert_eq!(partial_read_all(&data[..], vec![PartialOp::SplitAt(0)]).unwrap(),
+               vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn decoding_rejects_deeply_nested_chains() {
+    let nested = |depth: usize| {
+        let mut encoded = Vec::new();
+        for _ in 0..depth {
+            encoded.extend_from_slice(&[0x0e, 0x01]);
+        }
+        encoded.push(0x00);
+        encoded
+    };
+
+    assert!(decode_ops(&nested(MAX_CHAIN_DEPTH)).is_ok());
+    assert_eq!(decode_ops(&nested(MAX_CHAIN_DEPTH + 1)), Err(DecodeError::TooDeep));
+    assert_eq!(decode_ops(&nested(200_000)), Err(DecodeError::TooDeep));
+}
+
+#[test]
+fn stats_ignore_the_final_eof_read() {
+    let data = [1, 2, 3, 4, 5];
+    let mut r = PartialRead::new(&data[..], std::iter::repeat(PartialOp::Limited(2)));
+    assert_partial_read_eq!(r, data);
+    assert_eq!(r.stats().min_bytes_per_poll, Some(1));
+    assert_eq!(r.stats().max_bytes_per_poll, Some(2));
+}
+
+#[test]
+fn transform_write_reports_errors_of_the_inner_writer() {
+    let wk = unsafe { Waker::new_unchecked(noop_raw_waker()) };
+    let mut w = PartialWrite::new(VecWriter::new(), std::iter::repeat(PartialOp::Unlimited));
+    match w.poll_close(&wk) {
+        Poll::Ready(Ok(())) => {}
+        other => panic!("unexpected close result: {:?}", other),
+    }
+
+    let mut w = w.transform(|bytes| bytes.iter().map(|b| b ^ 0xff).collect());
+    match w.poll_write(&wk, &[1, 2, 3]) {
+        Poll::Ready(Err(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        other => panic!("expected a BrokenPipe error, got {:?}", other),
+    }
+    assert!(w.get_ref().get_ref().get_ref().is_empty());
+}